@@ -0,0 +1,235 @@
+//! Per frame cost of scanning for every detector in a single pass compared to the former scans,
+//! copied below: one pass for both mob colors as `identify_mobs` used to do, then one
+//! `pixel_detection` pass per target marker color and per status bar, each sending its pixels
+//! through a `sync_channel`.
+//!
+//! Also compares downscaled nameplate scanning with the full resolution scan.
+//!
+//! Run with `cargo bench --bench frame_scan`.
+
+use std::{
+    path::Path,
+    sync::mpsc::{sync_channel, Receiver},
+};
+
+use app::{
+    data::{Point, StatusBarConfig, StatusBarKind},
+    image_analyzer::{Color, FrameSource, ImageAnalyzer},
+    ipc::FarmingConfig,
+    platform::IGNORE_AREA_TOP,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use slog::Logger;
+
+const FRAMES: [&str; 2] = ["basic", "idle"];
+
+fn analyzer(frame: &str, config: &FarmingConfig) -> ImageAnalyzer {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures/frames")
+        .join(frame)
+        .join("frame_000.png");
+    let mut image_analyzer =
+        ImageAnalyzer::from_frame_source(FrameSource::from_path(path).unwrap(), None);
+    image_analyzer.capture_window(&Logger::root(slog::Discard, slog::o!()), config);
+    image_analyzer
+}
+
+/// Former `ImageAnalyzer::pixel_matches`.
+fn pixel_matches(c: &[u8; 4], r: &[u8; 3], tolerance: u8) -> bool {
+    let matches_inner = |a: u8, b: u8| match (a, b) {
+        (a, b) if a == b => true,
+        (a, b) if a > b => a.saturating_sub(b) <= tolerance,
+        (a, b) if a < b => b.saturating_sub(a) <= tolerance,
+        _ => false,
+    };
+    let perm = [(c[0], r[0]), (c[1], r[1]), (c[2], r[2])];
+    perm.iter().all(|&(a, b)| matches_inner(a, b))
+}
+
+/// Former `ImageAnalyzer::pixel_detection`.
+fn pixel_detection(
+    image_analyzer: &ImageAnalyzer,
+    colors: Vec<Color>,
+    min_x: u32,
+    min_y: u32,
+    mut max_x: u32,
+    mut max_y: u32,
+    tolerence: Option<u8>,
+) -> Receiver<Point> {
+    let (snd, recv) = sync_channel::<Point>(4096);
+    let image = image_analyzer.image().unwrap();
+    let ignore_area_bottom = image_analyzer.hud_layout().ignore_area_bottom;
+
+    if max_x == 0 {
+        max_x = image.width();
+    }
+
+    if max_y == 0 {
+        max_y = image.height();
+    }
+
+    image
+        .enumerate_rows()
+        .par_bridge()
+        .for_each(move |(y, row)| {
+            // Skip this row if it's in an ignored area
+            let image_height = image.height();
+            #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
+            if y <= IGNORE_AREA_TOP
+                || y > image_height
+                    .checked_sub(ignore_area_bottom)
+                    .unwrap_or(image_height)
+                || y > IGNORE_AREA_TOP + max_y
+                || y > max_y
+                || y < min_y
+            {
+                return;
+            }
+
+            // Loop over columns
+            'outer: for (x, _, px) in row {
+                if px.0[3] != 255 || x >= max_x {
+                    return;
+                } else if x < min_x {
+                    continue;
+                }
+
+                for ref_color in colors.iter() {
+                    // Check if the pixel matches any of the reference colors
+                    if pixel_matches(&px.0, &ref_color.refs, tolerence.unwrap_or(5)) {
+                        let _ = snd.send(Point::new(x, y));
+
+                        // Continue to next column
+                        continue 'outer;
+                    }
+                }
+            }
+        });
+    recv
+}
+
+/// Former pixel collection of `identify_mobs`, both mob colors in one pass.
+fn mob_pixels(image_analyzer: &ImageAnalyzer, config: &FarmingConfig) -> (Vec<Point>, Vec<Point>) {
+    let image = image_analyzer.image().unwrap();
+    let ignore_area_bottom = image_analyzer.hud_layout().ignore_area_bottom;
+    let [pas_r, pas_g, pas_b] = config.passive_mobs_colors();
+    let [agg_r, agg_g, agg_b] = config.aggressive_mobs_colors();
+    let ref_color_pas = [
+        pas_r.unwrap_or(234),
+        pas_g.unwrap_or(234),
+        pas_b.unwrap_or(149),
+    ];
+    let ref_color_agg = [agg_r.unwrap_or(179), agg_g.unwrap_or(23), agg_b.unwrap_or(23)];
+
+    let (snd, recv) = sync_channel::<(bool, Point)>(4096);
+    image
+        .enumerate_rows()
+        .par_bridge()
+        .for_each(move |(y, row)| {
+            #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
+            if y <= IGNORE_AREA_TOP || y > image.height() - ignore_area_bottom {
+                return;
+            }
+            for (x, _, px) in row {
+                if px.0[3] != 255 {
+                    return;
+                } else if x <= 250 && y <= 110 {
+                    // avoid detect the health bar as a monster
+                    continue;
+                }
+                if pixel_matches(&px.0, &ref_color_pas, config.passive_tolerence()) {
+                    let _ = snd.send((true, Point::new(x, y)));
+                } else if pixel_matches(&px.0, &ref_color_agg, config.aggressive_tolerence()) {
+                    let _ = snd.send((false, Point::new(x, y)));
+                }
+            }
+        });
+
+    let (mut passive, mut aggressive) = (vec![], vec![]);
+    while let Ok((is_passive, point)) = recv.recv() {
+        if is_passive {
+            passive.push(point);
+        } else {
+            aggressive.push(point);
+        }
+    }
+    (passive, aggressive)
+}
+
+/// Every pixel the single pass scanner looks for, found the way it was before.
+fn former_scans(
+    image_analyzer: &ImageAnalyzer,
+    config: &FarmingConfig,
+    status_bars: &[StatusBarConfig],
+) -> Vec<Vec<Point>> {
+    let (passive, aggressive) = mob_pixels(image_analyzer, config);
+
+    let mut pixels = vec![passive, aggressive];
+    for marker_color in [Color::new(246, 90, 106), Color::new(164, 180, 226)] {
+        let recv = pixel_detection(image_analyzer, vec![marker_color], 0, 0, 0, 0, None);
+        pixels.push(recv.iter().collect());
+    }
+    for bar in status_bars {
+        let recv = pixel_detection(
+            image_analyzer,
+            bar.refs.clone(),
+            bar.min_x,
+            bar.min_y,
+            bar.max_x,
+            bar.max_y,
+            Some(bar.tolerance),
+        );
+        pixels.push(recv.iter().collect());
+    }
+    pixels
+}
+
+fn bench_frame_scan(c: &mut Criterion) {
+    let config = FarmingConfig::default();
+    let mut group = c.benchmark_group("frame_scan");
+    for frame in FRAMES {
+        let image_analyzer = analyzer(frame, &config);
+        let image = image_analyzer.image().unwrap();
+        let ignore_area_bottom = image_analyzer.hud_layout().ignore_area_bottom;
+        let scanner = image_analyzer.frame_scanner(&config);
+        let hud_layout = image_analyzer.hud_layout();
+        let status_bars = [
+            StatusBarKind::Hp,
+            StatusBarKind::Mp,
+            StatusBarKind::Fp,
+            StatusBarKind::TargetHP,
+            StatusBarKind::TargetMP,
+        ]
+        .into_iter()
+        .map(|kind| StatusBarConfig::from_layout(kind, &hud_layout, &config.status_bar_colors()))
+        .collect::<Vec<_>>();
+
+        group.bench_with_input(
+            BenchmarkId::new("former_scans", frame),
+            &status_bars,
+            |b, status_bars| {
+                b.iter(|| black_box(former_scans(&image_analyzer, &config, status_bars)))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("single_pass", frame),
+            &scanner,
+            |b, scanner| b.iter(|| black_box(scanner.scan(image, ignore_area_bottom))),
+        );
+
+        let mut downscaled_config = serde_json::to_value(&config).unwrap();
+        downscaled_config["mobs_scan_downscale"] = serde_json::json!(2);
+        let downscaled_config: FarmingConfig = serde_json::from_value(downscaled_config).unwrap();
+        let downscaled = image_analyzer.frame_scanner(&downscaled_config);
+        group.bench_with_input(
+            BenchmarkId::new("single_pass_downscaled", frame),
+            &downscaled,
+            |b, scanner| b.iter(|| black_box(scanner.scan(image, ignore_area_bottom))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_frame_scan);
+criterion_main!(benches);
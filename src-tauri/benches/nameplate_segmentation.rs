@@ -0,0 +1,166 @@
+//! Compare the connected components nameplate segmentation with the former two pass
+//! `cluster_by_distance` merge on the frame fixtures.
+//!
+//! Before timing them, both are checked against the nameplates listed in the `expected.json` of
+//! every sequence. Connected components must find them all, the former merge is only reported
+//! for comparison. The fixtures are synthetic, their nameplates are solid rectangles without the
+//! letter gaps of real ones, so neither the check nor the timings tell which approach segments
+//! real nameplates better.
+//!
+//! Run with `cargo bench --bench nameplate_segmentation`.
+
+use std::{fs, path::PathBuf};
+
+use app::{
+    data::{point_selector, Bounds, PointCloud},
+    image_analyzer::{Detection, FrameSource, ImageAnalyzer},
+    ipc::FarmingConfig,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde::Deserialize;
+use slog::Logger;
+
+const FRAMES: [&str; 2] = ["basic", "idle"];
+
+#[derive(Deserialize)]
+struct ExpectedMob {
+    kind: String,
+    bounds: Bounds,
+}
+
+#[derive(Deserialize)]
+struct ExpectedFrame {
+    frame: String,
+    mobs: Vec<ExpectedMob>,
+}
+
+fn sequence_dir(sequence: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures/frames")
+        .join(sequence)
+}
+
+fn capture(image_analyzer: &mut ImageAnalyzer) {
+    image_analyzer.capture_window(
+        &Logger::root(slog::Discard, slog::o!()),
+        &FarmingConfig::default(),
+    );
+}
+
+fn pixels(image_analyzer: &ImageAnalyzer, detection: Detection) -> PointCloud {
+    image_analyzer.detected_pixels(detection).unwrap().clone()
+}
+
+fn mob_pixels(frame: &str) -> PointCloud {
+    let path = sequence_dir(frame).join("frame_000.png");
+    let mut image_analyzer =
+        ImageAnalyzer::from_frame_source(FrameSource::from_path(path).unwrap(), None);
+    capture(&mut image_analyzer);
+
+    PointCloud::new(
+        pixels(&image_analyzer, Detection::PassiveMobs)
+            .chain(pixels(&image_analyzer, Detection::AggressiveMobs))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Merge used before connected components, splits names with wide gaps and merges
+/// nameplates standing on top of each other.
+fn cluster_by_distance(cloud: &PointCloud) -> Vec<PointCloud> {
+    cloud
+        .cluster_by_distance(50, point_selector::x_axis)
+        .into_iter()
+        .flat_map(|x_cluster| x_cluster.cluster_by_distance(3, point_selector::y_axis))
+        .collect()
+}
+
+/// Bounds of the clusters the size of a nameplate, sorted.
+fn nameplate_bounds(clusters: Vec<PointCloud>, config: &FarmingConfig) -> Vec<(u32, u32, u32, u32)> {
+    let mut bounds = clusters
+        .iter()
+        .map(PointCloud::to_bounds)
+        .filter(|bounds| {
+            bounds.w > config.min_mobs_name_width() && bounds.w < config.max_mobs_name_width()
+        })
+        .map(|bounds| (bounds.x, bounds.y, bounds.w, bounds.h))
+        .collect::<Vec<_>>();
+    bounds.sort_unstable();
+    bounds
+}
+
+/// Segment every fixture frame with both approaches and compare them to the expected nameplates.
+fn check_bounds() {
+    let config = FarmingConfig::default();
+    let (dilate_x, dilate_y) = config.mobs_name_dilation();
+    let (mut expected_count, mut distance_matches) = (0, 0);
+
+    for sequence in FRAMES {
+        let dir = sequence_dir(sequence);
+        let expected_frames: Vec<ExpectedFrame> =
+            serde_json::from_reader(fs::File::open(dir.join("expected.json")).unwrap()).unwrap();
+        let mut image_analyzer =
+            ImageAnalyzer::from_frame_source(FrameSource::from_path(&dir).unwrap(), None);
+
+        for expected in expected_frames {
+            capture(&mut image_analyzer);
+            for (kind, detection) in [
+                ("passive", Detection::PassiveMobs),
+                ("aggressive", Detection::AggressiveMobs),
+            ] {
+                let cloud = pixels(&image_analyzer, detection);
+                let mut expected_bounds = expected
+                    .mobs
+                    .iter()
+                    .filter(|mob| mob.kind == kind)
+                    .map(|mob| (mob.bounds.x, mob.bounds.y, mob.bounds.w, mob.bounds.h))
+                    .collect::<Vec<_>>();
+                expected_bounds.sort_unstable();
+
+                assert_eq!(
+                    nameplate_bounds(cloud.connected_components(dilate_x, dilate_y), &config),
+                    expected_bounds,
+                    "{} nameplates of {}/{}",
+                    kind,
+                    sequence,
+                    expected.frame
+                );
+
+                let distance_bounds = nameplate_bounds(cluster_by_distance(&cloud), &config);
+                expected_count += expected_bounds.len();
+                distance_matches += expected_bounds
+                    .iter()
+                    .filter(|bounds| distance_bounds.contains(bounds))
+                    .count();
+            }
+        }
+    }
+
+    println!(
+        "cluster_by_distance found {} of {} synthetic nameplates, connected_components all of them",
+        distance_matches, expected_count
+    );
+}
+
+fn bench_segmentation(c: &mut Criterion) {
+    check_bounds();
+
+    let (dilate_x, dilate_y) = FarmingConfig::default().mobs_name_dilation();
+    let mut group = c.benchmark_group("nameplate_segmentation");
+    for frame in FRAMES {
+        let cloud = mob_pixels(frame);
+        group.bench_with_input(
+            BenchmarkId::new("cluster_by_distance", frame),
+            &cloud,
+            |b, cloud| b.iter(|| cluster_by_distance(black_box(cloud))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("connected_components", frame),
+            &cloud,
+            |b, cloud| b.iter(|| black_box(cloud).connected_components(dilate_x, dilate_y)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_segmentation);
+criterion_main!(benches);
@@ -4,6 +4,9 @@ pub use self::traits::*;
 mod farming_behavior;
 pub use self::farming_behavior::FarmingBehavior;
 
+mod buff_timers;
+mod skill_rotation;
+
 mod support_behavior;
 pub use self::support_behavior::SupportBehavior;
 
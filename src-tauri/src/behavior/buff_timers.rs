@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+use crate::ipc::{Slot, SlotBar};
+
+/// Buffs are recast when they lapse within this margin, casting takes a moment and the bot may be
+/// busy on the tick they run out
+pub const BUFF_RECAST_MARGIN: Duration = Duration::from_secs(3);
+
+/// Time the client takes to show the icon of a buff once it was cast
+pub const BUFF_ICON_DELAY: Duration = Duration::from_secs(1);
+
+/// When the buffs of slots with a buff duration run out.
+#[derive(Debug, Clone, Default)]
+pub struct BuffTimers {
+    expires_at: [[Option<Instant>; 10]; 9],
+    /// Whether the icon of a buff was seen on the last frame, `None` if it is not detected
+    detected: [[Option<bool>; 10]; 9],
+}
+
+impl BuffTimers {
+    /// Forget every buff, e.g. they were lost on death
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Store which buff icons the current frame shows.
+    pub fn set_detected(&mut self, detected: [[Option<bool>; 10]; 9]) {
+        self.detected = detected;
+    }
+
+    /// The buff of a slot was cast at `now`.
+    pub fn record_cast(&mut self, slot: &Slot, slot_index: (usize, usize), now: Instant) {
+        if let Some(duration) = slot.buff_duration() {
+            self.expires_at[slot_index.0][slot_index.1] = Some(now + duration);
+        }
+    }
+
+    /// Buff slot to recast, the one lapsing first. Buffs whose icon is missing and buffs never cast
+    /// lapse first, the timer only counts while the icon can't tell. `is_usable` tells whether a
+    /// slot can be pressed, e.g. it is not on cooldown.
+    pub fn next_recast(
+        &self,
+        slot_bars: &[SlotBar],
+        now: Instant,
+        is_usable: impl Fn((usize, usize), &Slot) -> bool,
+    ) -> Option<(usize, usize)> {
+        slot_bars
+            .iter()
+            .enumerate()
+            .flat_map(|(slot_bar_index, slot_bar)| {
+                slot_bar
+                    .slots()
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(slot_index, slot)| ((slot_bar_index, slot_index), slot))
+            })
+            .filter(|(slot_index, slot)| {
+                slot.buff_duration().is_some() && is_usable(*slot_index, slot)
+            })
+            .filter_map(|(slot_index, slot)| {
+                let expires_at = self.expires_at[slot_index.0][slot_index.1];
+                match self.detected[slot_index.0][slot_index.1] {
+                    Some(true) => None,
+                    // The icon shows up a moment after the cast
+                    Some(false) if !self.was_cast_recently(&slot, slot_index, now) => {
+                        Some((slot_index, None))
+                    }
+                    _ => {
+                        let is_lapsing = expires_at.map_or(true, |expires_at| {
+                            expires_at.saturating_duration_since(now) <= BUFF_RECAST_MARGIN
+                        });
+                        if is_lapsing {
+                            Some((slot_index, expires_at))
+                        } else {
+                            None
+                        }
+                    }
+                }
+            })
+            .min_by_key(|(_, expires_at)| *expires_at)
+            .map(|(slot_index, _)| slot_index)
+    }
+
+    fn was_cast_recently(&self, slot: &Slot, slot_index: (usize, usize), now: Instant) -> bool {
+        let expires_at = self.expires_at[slot_index.0][slot_index.1];
+        let cast_at = expires_at
+            .zip(slot.buff_duration())
+            .and_then(|(expires_at, duration)| expires_at.checked_sub(duration));
+        cast_at.map_or(false, |cast_at| {
+            now.saturating_duration_since(cast_at) < BUFF_ICON_DELAY
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+
+    use super::BuffTimers;
+    use crate::ipc::SlotBar;
+
+    #[test]
+    fn test_buff_timers() {
+        let buff = |duration: u32| json!({ "slot_type": "BuffSkill", "slot_buff_duration": duration, "slot_enabled": true });
+        let unused = json!({ "slot_type": "Unused", "slot_enabled": false });
+        let mut slots = vec![unused; 10];
+        slots[1] = buff(60_000);
+        slots[2] = buff(10_000);
+        // Buffs without a duration are not timed
+        slots[3] = buff(0);
+        let mut slot_bars =
+            vec![serde_json::from_value::<SlotBar>(json!({ "slots": slots })).unwrap()];
+        slot_bars.push(SlotBar::default());
+
+        let start = Instant::now();
+        let mut timers = BuffTimers::default();
+        let usable = |_: (usize, usize), _: &_| true;
+        assert_eq!(timers.next_recast(&slot_bars, start, usable), Some((0, 1)));
+
+        let slot = |slot_index: usize| slot_bars[0].slots()[slot_index];
+        timers.record_cast(&slot(1), (0, 1), start);
+        assert_eq!(timers.next_recast(&slot_bars, start, usable), Some((0, 2)));
+        timers.record_cast(&slot(2), (0, 2), start);
+        assert_eq!(timers.next_recast(&slot_bars, start, usable), None);
+
+        // The short buff is about to lapse, then both are
+        let soon = start + Duration::from_secs(8);
+        assert_eq!(timers.next_recast(&slot_bars, soon, usable), Some((0, 2)));
+        let later = start + Duration::from_secs(70);
+        assert_eq!(timers.next_recast(&slot_bars, later, usable), Some((0, 2)));
+        assert_eq!(
+            timers.next_recast(&slot_bars, later, |slot_index, _| slot_index != (0, 2)),
+            Some((0, 1))
+        );
+
+        timers.reset();
+        assert_eq!(timers.next_recast(&slot_bars, start, usable), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_detected_buffs() {
+        let buff =
+            json!({ "slot_type": "BuffSkill", "slot_buff_duration": 60_000, "slot_enabled": true });
+        let unused = json!({ "slot_type": "Unused", "slot_enabled": false });
+        let mut slots = vec![unused; 10];
+        slots[1] = buff.clone();
+        slots[2] = buff;
+        let slot_bars = vec![serde_json::from_value::<SlotBar>(json!({ "slots": slots })).unwrap()];
+        let slot = |slot_index: usize| slot_bars[0].slots()[slot_index];
+        let usable = |_: (usize, usize), _: &_| true;
+        let detected = |first: bool, second: bool| {
+            let mut detected = [[None; 10]; 9];
+            detected[0][1] = Some(first);
+            detected[0][2] = Some(second);
+            detected
+        };
+
+        let start = Instant::now();
+        let mut timers = BuffTimers::default();
+        timers.record_cast(&slot(1), (0, 1), start);
+        timers.record_cast(&slot(2), (0, 2), start);
+
+        // The icon is not drawn yet right after the cast
+        timers.set_detected(detected(false, true));
+        assert_eq!(timers.next_recast(&slot_bars, start, usable), None);
+
+        // Buff removed early, e.g. dispelled
+        let later = start + Duration::from_secs(10);
+        assert_eq!(timers.next_recast(&slot_bars, later, usable), Some((0, 1)));
+
+        // Still active past its duration, e.g. the duration is set too short
+        timers.set_detected(detected(true, true));
+        let lapsed = start + Duration::from_secs(70);
+        assert_eq!(timers.next_recast(&slot_bars, lapsed, usable), None);
+
+        // The timer takes over when the buff area can't tell
+        timers.set_detected([[None; 10]; 9]);
+        assert_eq!(timers.next_recast(&slot_bars, lapsed, usable), Some((0, 1)));
+    }
+}
@@ -1,18 +1,17 @@
 use std::time::{Duration, Instant};
 
-use rand::prelude::SliceRandom;
+use rand::{prelude::SliceRandom, rngs::StdRng, SeedableRng};
 use slog::Logger;
-use tauri::{Manager, Window};
 
-use super::Behavior;
+use super::{buff_timers::BuffTimers, skill_rotation::RotationProgress, Behavior};
 use crate::{
     data::{Bounds, MobType, Point, Target, TargetType},
-    image_analyzer::ImageAnalyzer,
-    ipc::{BotConfig, FarmingConfig, FrontendInfo, SlotType},
+    image_analyzer::{ActionBarState, ImageAnalyzer},
+    ipc::{BotConfig, FarmingConfig, FrontendInfo, SkillRotation, SlotType, SlotsState},
     movement::MovementAccessor,
-    platform::{eval_mob_click, send_slot_eval},
+    platform::InputSink,
     play,
-    utils::DateTime,
+    utils::{Clock, DateTime},
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -25,12 +24,19 @@ enum State {
 }
 
 pub struct FarmingBehavior<'a> {
-    rng: rand::rngs::ThreadRng,
+    rng: StdRng,
     logger: &'a Logger,
     movement: &'a MovementAccessor,
-    window: &'a Window,
+    input: &'a dyn InputSink,
+    clock: &'a dyn Clock,
     state: State,
     slots_usage_last_time: [[Option<Instant>; 10]; 9],
+    slots_usage_count: [[u32; 10]; 9],
+    /// What was seen of the slot bars, e.g. empty slots and cooldowns
+    action_bar: ActionBarState,
+    /// Step of the skill rotation against the current target
+    rotation: RotationProgress,
+    buff_timers: BuffTimers,
     last_initial_attack_time: Instant,
     last_kill_time: Instant,
     avoided_bounds: Vec<(Bounds, Instant, u128)>,
@@ -46,19 +52,32 @@ pub struct FarmingBehavior<'a> {
     last_click_pos: Option<Point>,
     stealed_target_count: u32,
     last_no_ennemy_time: Option<Instant>,
+    is_mobs_timeout_reached: bool,
+    last_detected_mobs: Vec<Target>,
+    last_target_marker: Option<Bounds>,
 }
 
 impl<'a> Behavior<'a> for FarmingBehavior<'a> {
-    fn new(logger: &'a Logger, movement: &'a MovementAccessor, window: &'a Window) -> Self {
+    fn new(
+        logger: &'a Logger,
+        movement: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self {
         Self {
             logger,
             movement,
-            window,
-            rng: rand::thread_rng(),
+            input,
+            clock,
+            rng: StdRng::from_entropy(),
             state: State::SearchingForEnemy,
             slots_usage_last_time: [[None; 10]; 9],
-            last_initial_attack_time: Instant::now(),
-            last_kill_time: Instant::now(),
+            slots_usage_count: [[0; 10]; 9],
+            action_bar: ActionBarState::default(),
+            rotation: RotationProgress::default(),
+            buff_timers: BuffTimers::default(),
+            last_initial_attack_time: clock.now(),
+            last_kill_time: clock.now(),
             avoided_bounds: vec![],
             is_attacking: false,
             rotation_movement_tries: 0,
@@ -66,19 +85,26 @@ impl<'a> Behavior<'a> for FarmingBehavior<'a> {
             obstacle_avoidance_count: 0,
             last_summon_pet_time: None,
             last_killed_type: MobType::Passive,
-            start_time: Instant::now(),
+            start_time: clock.now(),
             already_attack_count: 0,
-            last_buff_usage: Instant::now(),
+            last_buff_usage: clock.now(),
             last_click_pos: None,
             stealed_target_count: 0,
             last_no_ennemy_time: None,
+            is_mobs_timeout_reached: false,
+            last_detected_mobs: vec![],
+            last_target_marker: None,
         }
     }
 
     fn start(&mut self, _config: &BotConfig) {}
-    fn update(&mut self, _config: &BotConfig) {}
+    fn update(&mut self, _config: &BotConfig) {
+        // Slots may have been reassigned
+        self.action_bar.reset();
+    }
     fn stop(&mut self, _config: &BotConfig) {
         self.slots_usage_last_time = [[None; 10]; 9];
+        self.buff_timers.reset();
     }
 
     fn run_iteration(
@@ -92,8 +118,12 @@ impl<'a> Behavior<'a> for FarmingBehavior<'a> {
         self.update_timestamps(config);
 
         // Check whether something should be restored
+        self.update_action_bar(config, image);
+        self.update_buffs(config, image);
         self.check_restorations(config, image);
 
+        self.last_target_marker = None;
+
         // Check state machine
         self.state = match self.state {
             State::NoEnemyFound => self.on_no_enemy_found(config),
@@ -104,10 +134,69 @@ impl<'a> Behavior<'a> for FarmingBehavior<'a> {
         };
 
         frontend_info.set_is_attacking(self.is_attacking);
+        frontend_info.set_slots_usage(&config.slot_bars(), self.slots_usage_count);
+        self.update_debug_overlay(frontend_info);
     }
 }
 
 impl FarmingBehavior<'_> {
+    /// Seed the random generator used for movements, e.g. to replay a session deterministically
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Name of the current state, e.g. for recordings
+    pub fn state_name(&self) -> String {
+        format!("{:?}", self.state)
+    }
+
+    /// Name of the current state without its target, e.g. for session statistics
+    pub fn state_kind(&self) -> &'static str {
+        match self.state {
+            State::NoEnemyFound => "NoEnemyFound",
+            State::SearchingForEnemy => "SearchingForEnemy",
+            State::EnemyFound(_) => "EnemyFound",
+            State::Attacking(_) => "Attacking",
+            State::AfterEnemyKill(_) => "AfterEnemyKill",
+        }
+    }
+
+    /// Forget how often slots were used, e.g. once the user refilled them
+    pub fn reset_slots_usage(&mut self) {
+        self.slots_usage_count = [[0; 10]; 9];
+    }
+
+    /// Forget which buffs are active, e.g. they were lost on death
+    pub fn reset_buff_timers(&mut self) {
+        self.buff_timers.reset();
+    }
+
+    /// Enemies killed since the behavior was created
+    pub fn kill_count(&self) -> u32 {
+        self.kill_count
+    }
+
+    /// Whether no mob was found for longer than the configured mobs timeout
+    pub fn is_mobs_timeout_reached(&self) -> bool {
+        self.is_mobs_timeout_reached
+    }
+
+    /// Send what was detected and chosen to the frontend debug overlay
+    fn update_debug_overlay(&self, frontend_info: &mut FrontendInfo) {
+        frontend_info.set_enemy_bounds(self.last_detected_mobs.clone());
+        frontend_info.set_active_enemy_bounds(match self.state {
+            State::EnemyFound(mob) | State::Attacking(mob) => Some(mob),
+            _ => None,
+        });
+        frontend_info.set_target_marker_bounds(self.last_target_marker);
+        frontend_info.set_avoided_bounds(
+            self.avoided_bounds
+                .iter()
+                .map(|(bounds, _, _)| *bounds)
+                .collect(),
+        );
+    }
+
     fn update_timestamps(&mut self, config: &FarmingConfig) {
         self.update_pickup_pet(config);
 
@@ -121,7 +210,7 @@ impl FarmingBehavior<'_> {
         let mut result: Vec<(Bounds, Instant, u128)> = vec![];
         for n in 0..self.avoided_bounds.len() {
             let current = self.avoided_bounds[n];
-            if current.1.elapsed().as_millis() < current.2 {
+            if self.clock.elapsed(current.1).as_millis() < current.2 {
                 result.push(current);
             }
         }
@@ -132,40 +221,53 @@ impl FarmingBehavior<'_> {
     fn update_pickup_pet(&mut self, config: &FarmingConfig) {
         if let Some(pickup_pet_slot_index) = config.slot_index(SlotType::PickupPet) {
             if let Some(last_time) = self.last_summon_pet_time {
-                if last_time.elapsed().as_millis()
+                if self.clock.elapsed(last_time).as_millis()
                     > config
                         .get_slot_cooldown(pickup_pet_slot_index.0, pickup_pet_slot_index.1)
                         .unwrap_or(3000) as u128
                 {
-                    send_slot_eval(
-                        self.window,
-                        pickup_pet_slot_index.0,
-                        pickup_pet_slot_index.1,
-                    );
+                    self.press_slot(pickup_pet_slot_index);
                     self.last_summon_pet_time = None;
                 }
             }
         }
     }
 
-    /// Update slots cooldown timers
-    fn update_slots_usage(&mut self, config: &FarmingConfig) {
-        for (slotbar_index, slot_bars) in self.slots_usage_last_time.into_iter().enumerate() {
-            for (slot_index, last_time) in slot_bars.into_iter().enumerate() {
-                let cooldown = config
-                    .get_slot_cooldown(slotbar_index, slot_index)
-                    .unwrap_or(100)
-                    .try_into();
-                if let Some(last_time) = last_time {
-                    if let Ok(cooldown) = cooldown {
-                        let slot_last_time = last_time.elapsed().as_millis();
-                        if slot_last_time > cooldown {
-                            self.slots_usage_last_time[slotbar_index][slot_index] = None;
-                        }
-                    }
+    /// Remember what the visible slot bar looks like
+    fn update_action_bar(&mut self, config: &FarmingConfig, image: &ImageAnalyzer) {
+        if !config.empty_slots_detection() && !config.cooldown_detection() {
+            self.action_bar.reset();
+            return;
+        }
+        if let Some(appearances) = image.action_bar_slots() {
+            self.action_bar.update(appearances);
+        }
+    }
+
+    /// Look for the icons of the buffs with a duration in the buff area
+    fn update_buffs(&mut self, config: &FarmingConfig, image: &ImageAnalyzer) {
+        let mut detected = [[None; 10]; 9];
+        if config.buff_detection() {
+            for buff_icon in config.buff_icons() {
+                if let Some(is_active) = detected
+                    .get_mut(buff_icon.slot_bar_index)
+                    .and_then(|slot_bar| slot_bar.get_mut(buff_icon.slot_index))
+                {
+                    *is_active = image.is_icon_visible(&buff_icon.icon);
                 }
             }
         }
+        self.buff_timers.set_detected(detected);
+    }
+
+    /// Update slots cooldown timers
+    fn update_slots_usage(&mut self, config: &FarmingConfig) {
+        self.action_bar.release_ready_slots(
+            &config.slot_bars(),
+            &mut self.slots_usage_last_time,
+            config.cooldown_detection(),
+            self.clock.now(),
+        );
     }
 
     fn get_slot_for(
@@ -175,9 +277,16 @@ impl FarmingBehavior<'_> {
         slot_type: SlotType,
         send: bool,
     ) -> Option<(usize, usize)> {
-        if let Some(slot_index) =
-            config.get_usable_slot_index(slot_type, threshold, self.slots_usage_last_time)
-        {
+        let slots_state = SlotsState {
+            last_usage: self.slots_usage_last_time,
+            usage_count: self.slots_usage_count,
+            empty: if config.empty_slots_detection() {
+                self.action_bar.empty_slots()
+            } else {
+                [[false; 10]; 9]
+            },
+        };
+        if let Some(slot_index) = config.get_usable_slot_index(slot_type, threshold, &slots_state) {
             if send {
                 //slog::debug!(self.logger, "Slot usage"; "slot_type" => slot_type.to_string(), "value" => threshold);
                 self.send_slot(slot_index);
@@ -190,9 +299,54 @@ impl FarmingBehavior<'_> {
 
     fn send_slot(&mut self, slot_index: (usize, usize)) {
         // Send keystroke for first slot mapped to pill
-        send_slot_eval(self.window, slot_index.0, slot_index.1);
+        self.press_slot(slot_index);
         // Update usage last time
-        self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(Instant::now());
+        self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(self.clock.now());
+        self.slots_usage_count[slot_index.0][slot_index.1] += 1;
+    }
+
+    /// Use the current step of the skill rotation. Steps that can't be used (e.g. disabled or
+    /// exhausted) are skipped, the rotation waits for skills on cooldown. Returns false when the
+    /// rotation has nothing to use.
+    fn use_rotation(
+        &mut self,
+        config: &FarmingConfig,
+        rotation: &SkillRotation,
+        target_hp: u32,
+    ) -> bool {
+        for _ in 0..rotation.len() {
+            let step = match self.rotation.current(rotation, target_hp) {
+                Some(step) => step,
+                None => return false,
+            };
+            let slot_index = (step.slot_bar_index, step.slot_index);
+            let slot = config
+                .slot_bars()
+                .get(slot_index.0)
+                .and_then(|slot_bar| slot_bar.slots().get(slot_index.1).copied());
+            let is_usable = slot.map_or(false, |slot| {
+                slot.slot_type() == SlotType::AttackSkill
+                    && slot.is_enabled()
+                    && !slot.is_exhausted(self.slots_usage_count[slot_index.0][slot_index.1])
+            });
+            if !is_usable {
+                self.rotation.advance();
+                continue;
+            }
+
+            if self.slots_usage_last_time[slot_index.0][slot_index.1].is_none() {
+                self.send_slot(slot_index);
+                self.rotation.advance();
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Press a slot without cooldown or usage tracking
+    fn press_slot(&mut self, slot_index: (usize, usize)) {
+        self.input.send_slot(slot_index.0, slot_index.1);
+        self.action_bar.set_visible_slot_bar(slot_index.0);
     }
 
     /// Pickup items on the ground.
@@ -200,17 +354,17 @@ impl FarmingBehavior<'_> {
         let slot = self.get_slot_for(config, None, SlotType::PickupPet, false);
         if let Some(index) = slot {
             if self.last_summon_pet_time.is_none() {
-                send_slot_eval(self.window, index.0, index.1);
-                self.last_summon_pet_time = Some(Instant::now());
+                self.press_slot(index);
+                self.last_summon_pet_time = Some(self.clock.now());
             } else {
                 // if pet is already out, just reset it's timer
-                self.last_summon_pet_time = Some(Instant::now());
+                self.last_summon_pet_time = Some(self.clock.now());
             }
         } else {
             let slot = self.get_slot_for(config, None, SlotType::PickupMotion, false);
             if let Some(index) = slot {
                 for _i in 1..7 {
-                    send_slot_eval(self.window, index.0, index.1);
+                    self.press_slot(index);
                 }
             }
         }
@@ -241,22 +395,39 @@ impl FarmingBehavior<'_> {
     }
 
     fn check_buffs(&mut self, config: &FarmingConfig) {
-        if self.last_buff_usage.elapsed().as_millis() > config.interval_between_buffs() {
-            self.last_buff_usage = Instant::now();
+        // Buffs with a duration are recast right before they lapse
+        let now = self.clock.now();
+        let slot_bars = config.slot_bars();
+        let recast = self
+            .buff_timers
+            .next_recast(&slot_bars, now, |slot_index, slot| {
+                slot.is_enabled()
+                    && self.slots_usage_last_time[slot_index.0][slot_index.1].is_none()
+                    && !slot.is_exhausted(self.slots_usage_count[slot_index.0][slot_index.1])
+            });
+        if let Some(slot_index) = recast {
+            self.send_slot(slot_index);
+            let slot = slot_bars[slot_index.0].slots()[slot_index.1];
+            self.buff_timers.record_cast(&slot, slot_index, now);
+            self.clock.sleep(Duration::from_millis(100));
+        } else if self.clock.elapsed(self.last_buff_usage).as_millis()
+            > config.interval_between_buffs()
+        {
+            self.last_buff_usage = self.clock.now();
             self.get_slot_for(config, None, SlotType::BuffSkill, true);
-            std::thread::sleep(Duration::from_millis(100));
+            self.clock.sleep(Duration::from_millis(100));
         }
     }
 
     fn on_no_enemy_found(&mut self, config: &FarmingConfig) -> State {
         if let Some(last_no_ennemy_time) = self.last_no_ennemy_time {
             if config.mobs_timeout() > 0
-                && last_no_ennemy_time.elapsed().as_millis() > config.mobs_timeout()
+                && self.clock.elapsed(last_no_ennemy_time).as_millis() > config.mobs_timeout()
             {
-                self.window.app_handle().exit(0);
+                self.is_mobs_timeout_reached = true;
             }
         } else {
-            self.last_no_ennemy_time = Some(Instant::now());
+            self.last_no_ennemy_time = Some(self.clock.now());
         }
         use crate::movement::prelude::*;
         // Try rotating first in order to locate nearby enemies
@@ -307,6 +478,7 @@ impl FarmingBehavior<'_> {
             return State::Attacking(Target::default());
         }
         let mobs = image.identify_mobs(config);
+        self.last_detected_mobs = mobs.clone();
         if mobs.is_empty() {
             // Transition to next state
             State::NoEnemyFound
@@ -328,7 +500,7 @@ impl FarmingBehavior<'_> {
             if (mob_list.is_empty()
                 || self.last_killed_type == MobType::Aggressive
                     && mob_list.len() == 1
-                    && self.last_kill_time.elapsed().as_millis() < 5000)
+                    && self.clock.elapsed(self.last_kill_time).as_millis() < 5000)
                 && image.client_stats.hp.value >= config.min_hp_attack()
             {
                 mob_list = mobs
@@ -371,7 +543,7 @@ impl FarmingBehavior<'_> {
     fn avoid_last_click(&mut self) {
         if let Some(point) = self.last_click_pos {
             let marker = Bounds::new(point.x - 1, point.y - 1, 2, 2);
-            self.avoided_bounds.push((marker, Instant::now(), 5000));
+            self.avoided_bounds.push((marker, self.clock.now(), 5000));
         }
     }
 
@@ -382,10 +554,10 @@ impl FarmingBehavior<'_> {
         self.last_click_pos = Some(point);
 
         // Set cursor position and simulate a click
-        eval_mob_click(self.window, point);
+        self.input.mouse_click(point);
 
         // Wait a few ms before transitioning state
-        std::thread::sleep(Duration::from_millis(500));
+        self.clock.sleep(Duration::from_millis(500));
         State::Attacking(mob)
     }
 
@@ -398,7 +570,7 @@ impl FarmingBehavior<'_> {
             if let Some(marker) = image.identify_target_marker(false) {
                 self.avoided_bounds.push((
                     marker.bounds.grow_by(self.already_attack_count * 10),
-                    Instant::now(),
+                    self.clock.now(),
                     2000,
                 ));
                 self.already_attack_count += 1;
@@ -435,7 +607,10 @@ impl FarmingBehavior<'_> {
                 ]);
             }
 
-            image.client_stats.target_hp.reset_last_update_time();
+            image
+                .client_stats
+                .target_hp
+                .reset_last_update_time(self.clock.now());
             self.obstacle_avoidance_count += 1;
             false
         } else {
@@ -454,7 +629,9 @@ impl FarmingBehavior<'_> {
             image.client_stats.target_hp.value == 100 && image.client_stats.target_mp.value == 0;
         let is_mob =
             image.client_stats.target_hp.value > 0 && image.client_stats.target_mp.value > 0;
-        let is_mob_alive = image.identify_target_marker(false).is_some()
+        let target_marker = image.identify_target_marker(false);
+        self.last_target_marker = target_marker.map(|marker| marker.bounds);
+        let is_mob_alive = target_marker.is_some()
             || image.client_stats.target_mp.value > 0
             || image.client_stats.target_hp.value > 0;
 
@@ -469,7 +646,7 @@ impl FarmingBehavior<'_> {
                 // Detect if mob was attacked
                 if image.client_stats.target_hp.value < 100 && config.prevent_already_attacked() {
                     // If we didn't took any damages abort attack
-                    if hp_last_update.elapsed().as_millis() > 5000 {
+                    if self.clock.elapsed(hp_last_update).as_millis() > 5000 {
                         return self.abort_attack(image);
                     } else if self.stealed_target_count > 5 {
                         self.stealed_target_count = 0;
@@ -489,23 +666,21 @@ impl FarmingBehavior<'_> {
             // Engagin combat
             if !self.is_attacking {
                 self.obstacle_avoidance_count = 0;
-                self.last_initial_attack_time = Instant::now();
+                self.last_initial_attack_time = self.clock.now();
                 self.is_attacking = true;
                 self.already_attack_count = 0;
+                self.rotation.restart();
             }
             // Use buffs only when target is found so we don't waste them
             self.check_buffs(config);
 
-            let last_target_hp_update = image
-                .client_stats
-                .target_hp
-                .last_update_time
-                .unwrap()
-                .elapsed()
+            let last_target_hp_update = self
+                .clock
+                .elapsed(image.client_stats.target_hp.last_update_time.unwrap())
                 .as_millis();
 
             // Obstacle avoidance
-            if image.identify_target_marker(false).is_none()
+            if target_marker.is_none()
                 || last_target_hp_update > config.obstacle_avoidance_cooldown()
             {
                 if image.client_stats.target_hp.value == 100 {
@@ -517,8 +692,14 @@ impl FarmingBehavior<'_> {
                 }
             }
 
-            // Try to use attack skill if at least one is selected in slot bar
-            self.get_slot_for(config, None, SlotType::AttackSkill, true);
+            // Follow the skill rotation, or use any attack skill selected in slot bar
+            let target_hp = image.client_stats.target_hp.value;
+            let used_rotation = config.skill_rotation().map_or(false, |rotation| {
+                self.use_rotation(config, rotation, target_hp)
+            });
+            if !used_rotation {
+                self.get_slot_for(config, None, SlotType::AttackSkill, true);
+            }
 
             self.state
         } else if !is_mob_alive && image.client_stats.is_alive() && self.is_attacking {
@@ -539,11 +720,11 @@ impl FarmingBehavior<'_> {
 
     fn after_enemy_kill_debug(&mut self, frontend_info: &mut FrontendInfo) {
         // Let's introduce some stats
-        let started_elapsed = self.start_time.elapsed();
+        let started_elapsed = self.clock.elapsed(self.start_time);
         let started_formatted = DateTime::format_time(started_elapsed);
 
-        let elapsed_time_to_kill = self.last_initial_attack_time.elapsed();
-        let elapsed_search_time = self.last_kill_time.elapsed() - elapsed_time_to_kill;
+        let elapsed_time_to_kill = self.clock.elapsed(self.last_initial_attack_time);
+        let elapsed_search_time = self.clock.elapsed(self.last_kill_time) - elapsed_time_to_kill;
 
         let search_time_as_secs = {
             if self.kill_count > 0 {
@@ -588,7 +769,7 @@ impl FarmingBehavior<'_> {
         self.after_enemy_kill_debug(frontend_info);
 
         self.stealed_target_count = 0;
-        self.last_kill_time = Instant::now();
+        self.last_kill_time = self.clock.now();
 
         // Pickup items
         self.pickup_items(config);
@@ -597,3 +778,372 @@ impl FarmingBehavior<'_> {
         State::SearchingForEnemy
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc, time::Duration};
+
+    use rand::{prelude::SliceRandom, rngs::StdRng, SeedableRng};
+    use serde_json::{json, Value};
+    use slog::Logger;
+
+    use super::{FarmingBehavior, State};
+    use crate::{
+        behavior::Behavior,
+        data::{Bounds, Point},
+        image_analyzer::{FrameSource, ImageAnalyzer},
+        ipc::{BotConfig, FrontendInfo, SlotType},
+        movement::MovementAccessor,
+        platform::{InputEvent, KeyMode, RecordingInputSink},
+        utils::{Clock, ManualClock},
+    };
+
+    const SEED: u64 = 42;
+
+    fn fixture(path: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/frames")
+            .join(path)
+    }
+
+    fn key(key: &str, mode: KeyMode) -> InputEvent {
+        InputEvent::Key(key.to_string(), mode)
+    }
+
+    /// Farming bot config with every slot unused, `farming_config` fields are merged in
+    fn config_with(farming_config: Value) -> BotConfig {
+        let unused_slot = json!({ "slot_type": "Unused", "slot_enabled": false });
+        let mut farming =
+            json!({ "slot_bars": vec![json!({ "slots": vec![unused_slot; 10] }); 9] });
+        if let (Some(farming), Value::Object(fields)) = (farming.as_object_mut(), farming_config) {
+            farming.extend(fields);
+        }
+
+        serde_json::from_value(json!({
+            "change_id": 0,
+            "is_running": true,
+            "mode": "Farming",
+            "farming_config": farming,
+            "support_config": {},
+            "shout_config": {},
+        }))
+        .unwrap()
+    }
+
+    /// Farming config with a pill in the first slot of the first slot bar
+    fn config_with_pill(threshold: u32) -> BotConfig {
+        let mut config = serde_json::to_value(config_with(json!({}))).unwrap();
+        config["farming_config"]["slot_bars"][0]["slots"][0] =
+            json!({ "slot_type": "Pill", "slot_threshold": threshold, "slot_enabled": true });
+        serde_json::from_value(config).unwrap()
+    }
+
+    /// Bot environment driven by a manual clock, a seeded RNG and a recording input sink.
+    struct Simulation {
+        logger: Logger,
+        input: Arc<RecordingInputSink>,
+        clock: Arc<ManualClock>,
+        movement: MovementAccessor,
+    }
+
+    impl Simulation {
+        fn new() -> Self {
+            let input = Arc::new(RecordingInputSink::new());
+            let clock = Arc::new(ManualClock::new());
+            let movement = MovementAccessor::new(input.clone(), clock.clone());
+            movement.set_rng_seed(SEED);
+
+            Self {
+                logger: Logger::root(slog::Discard, slog::o!()),
+                movement,
+                input,
+                clock,
+            }
+        }
+
+        fn behavior(&self) -> FarmingBehavior<'_> {
+            let mut behavior =
+                FarmingBehavior::new(&self.logger, &self.movement, &*self.input, &*self.clock);
+            behavior.set_rng_seed(SEED);
+            behavior
+        }
+
+        /// Analyzer replaying a single frame, stats are scripted with `set_stats`.
+        fn analyzer(&self, frame: &str) -> ImageAnalyzer {
+            let mut analyzer = ImageAnalyzer::from_frame_source(
+                FrameSource::from_path(fixture(frame)).unwrap(),
+                Some(self.input.clone()),
+            );
+            analyzer.capture_window(&self.logger, config_with(json!({})).farming_config());
+            analyzer
+        }
+
+        /// Script player and target stats as if they were just read from the client.
+        fn set_stats(&self, image: &mut ImageAnalyzer, hp: u32, target_hp: u32, target_mp: u32) {
+            let now = self.clock.now();
+            let stats = &mut image.client_stats;
+            for (stat, value) in [
+                (&mut stats.hp, hp),
+                (&mut stats.mp, 100),
+                (&mut stats.fp, 100),
+                (&mut stats.target_hp, target_hp),
+                (&mut stats.target_mp, target_mp),
+            ] {
+                stat.value = value;
+                stat.reset_last_update_time(now);
+            }
+        }
+
+        /// Run a single iteration and return the inputs it sent.
+        fn tick(
+            &self,
+            behavior: &mut FarmingBehavior,
+            config: &BotConfig,
+            image: &mut ImageAnalyzer,
+        ) -> Vec<InputEvent> {
+            behavior.run_iteration(&mut FrontendInfo::default(), config, image);
+            self.input.take()
+        }
+
+        /// Walk a fresh behavior from searching to attacking the closest mob of `basic/frame_000`.
+        fn engage(
+            &self,
+            behavior: &mut FarmingBehavior,
+            config: &BotConfig,
+            image: &mut ImageAnalyzer,
+        ) {
+            assert_eq!(self.tick(behavior, config, image), vec![]);
+            assert!(matches!(behavior.state, State::EnemyFound(_)));
+            assert_eq!(
+                self.tick(behavior, config, image),
+                vec![InputEvent::MouseClick(Point::new(179, 367))]
+            );
+            assert!(matches!(behavior.state, State::Attacking(_)));
+        }
+    }
+
+    #[test]
+    fn test_emitted_inputs() {
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let input = Arc::new(RecordingInputSink::new());
+        let clock = Arc::new(ManualClock::new());
+        let movement = MovementAccessor::new(input.clone(), clock.clone());
+        let mut behavior = FarmingBehavior::new(&logger, &movement, &*input, &*clock);
+        let mut frontend_info = FrontendInfo::default();
+        let config = config_with_pill(60);
+
+        let mut analyzer = ImageAnalyzer::from_frame_source(
+            FrameSource::from_path(fixture("basic")).unwrap(),
+            Some(input.clone()),
+        );
+
+        let expected_inputs = [
+            // Full stats, the closest aggressive mob gets selected
+            vec![],
+            // HP dropped to 50% so the pill is used before clicking the selected mob
+            vec![
+                key("F1", KeyMode::Press),
+                key("0", KeyMode::Press),
+                InputEvent::MouseClick(Point::new(179, 367)),
+            ],
+        ];
+
+        for expected in expected_inputs {
+            analyzer.capture_window(&logger, config.farming_config());
+            analyzer.client_stats.update(&analyzer.clone(), &logger);
+            behavior.run_iteration(&mut frontend_info, &config, &mut analyzer);
+            assert_eq!(input.take(), expected);
+        }
+        assert_eq!(input.take_slots(), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_obstacle_avoidance() {
+        let sim = Simulation::new();
+        let mut image = sim.analyzer("basic/frame_000.png");
+        let mut behavior = sim.behavior();
+        let config = config_with(json!({}));
+        sim.set_stats(&mut image, 100, 100, 100);
+        sim.engage(&mut behavior, &config, &mut image);
+
+        // No target marker in sight, jump forward first
+        let start = sim.clock.now();
+        assert_eq!(
+            sim.tick(&mut behavior, &config, &mut image),
+            vec![
+                key("Z", KeyMode::Press),
+                key("W", KeyMode::Hold),
+                key("Space", KeyMode::Hold),
+                key("Space", KeyMode::Release),
+                key("W", KeyMode::Release),
+            ]
+        );
+        assert_eq!(sim.clock.elapsed(start), Duration::from_millis(800));
+
+        // Then strafe in a random direction while jumping
+        let side = *["A", "D"].choose(&mut StdRng::seed_from_u64(SEED)).unwrap();
+        assert_eq!(
+            sim.tick(&mut behavior, &config, &mut image),
+            vec![
+                key("W", KeyMode::Hold),
+                key("Space", KeyMode::Hold),
+                key(side, KeyMode::Hold),
+                key(side, KeyMode::Release),
+                key("Space", KeyMode::Release),
+                key("W", KeyMode::Release),
+                key("Z", KeyMode::Press),
+            ]
+        );
+
+        // Full HP target is given up after two tries and its position avoided
+        assert_eq!(
+            sim.tick(&mut behavior, &config, &mut image),
+            vec![key("Escape", KeyMode::Press)]
+        );
+        assert!(matches!(behavior.state, State::SearchingForEnemy));
+        assert_eq!(behavior.avoided_bounds.len(), 1);
+        assert_eq!(behavior.avoided_bounds[0].0, Bounds::new(178, 366, 2, 2));
+    }
+
+    #[test]
+    fn test_stolen_target_abort() {
+        let sim = Simulation::new();
+        let mut image = sim.analyzer("basic/frame_000.png");
+        let mut behavior = sim.behavior();
+        let config = config_with(json!({ "prevent_already_attacked": true }));
+        sim.set_stats(&mut image, 100, 100, 100);
+        sim.engage(&mut behavior, &config, &mut image);
+
+        // Target is already damaged but we did not take any damage for a while
+        sim.set_stats(&mut image, 100, 80, 100);
+        sim.clock.advance(Duration::from_millis(5001));
+
+        assert_eq!(
+            sim.tick(&mut behavior, &config, &mut image),
+            vec![key("Escape", KeyMode::Press)]
+        );
+        assert!(matches!(behavior.state, State::SearchingForEnemy));
+        assert!(!behavior.is_attacking);
+        assert_eq!(behavior.avoided_bounds.len(), 1);
+    }
+
+    #[test]
+    fn test_npc_detection() {
+        let sim = Simulation::new();
+        let mut image = sim.analyzer("basic/frame_000.png");
+        let mut behavior = sim.behavior();
+        let config = config_with(json!({}));
+        sim.set_stats(&mut image, 100, 100, 100);
+        sim.engage(&mut behavior, &config, &mut image);
+
+        // NPCs have a full HP bar but no MP bar
+        sim.set_stats(&mut image, 100, 100, 0);
+        assert_eq!(sim.tick(&mut behavior, &config, &mut image), vec![]);
+        assert!(matches!(behavior.state, State::SearchingForEnemy));
+        assert!(!behavior.is_attacking);
+        assert_eq!(behavior.avoided_bounds[0].0, Bounds::new(178, 366, 2, 2));
+    }
+
+    #[test]
+    fn test_mobs_timeout_exit() {
+        let sim = Simulation::new();
+        let mut image = sim.analyzer("idle/frame_000.png");
+        let mut behavior = sim.behavior();
+        let config = config_with(json!({ "mobs_timeout": 10000 }));
+        sim.set_stats(&mut image, 100, 0, 0);
+
+        // Nothing on screen, the bot keeps rotating to look for mobs
+        for _ in 0..10 {
+            sim.tick(&mut behavior, &config, &mut image);
+            assert!(!behavior.is_mobs_timeout_reached());
+        }
+        assert!(matches!(behavior.state, State::SearchingForEnemy));
+
+        sim.clock.advance(Duration::from_secs(10));
+        sim.tick(&mut behavior, &config, &mut image);
+        sim.tick(&mut behavior, &config, &mut image);
+        assert!(behavior.is_mobs_timeout_reached());
+    }
+
+    #[test]
+    fn test_slot_max_uses() {
+        let sim = Simulation::new();
+        let mut image = sim.analyzer("basic/frame_000.png");
+        let mut behavior = sim.behavior();
+        let mut config = serde_json::to_value(config_with_pill(60)).unwrap();
+        config["farming_config"]["slot_bars"][0]["slots"][0]["slot_max_uses"] = json!(1);
+        let config: BotConfig = serde_json::from_value(config).unwrap();
+        sim.set_stats(&mut image, 50, 100, 100);
+
+        assert_eq!(
+            sim.tick(&mut behavior, &config, &mut image),
+            vec![key("F1", KeyMode::Press), key("0", KeyMode::Press)]
+        );
+
+        // Cooldown is over but the only pill was used
+        sim.clock.advance(Duration::from_millis(200));
+        assert_eq!(
+            sim.tick(&mut behavior, &config, &mut image),
+            vec![InputEvent::MouseClick(Point::new(179, 367))]
+        );
+        let farming_config = config.farming_config();
+        assert_eq!(
+            behavior.get_slot_for(farming_config, Some(50), SlotType::Pill, false),
+            None
+        );
+
+        behavior.reset_slots_usage();
+        assert_eq!(
+            behavior.get_slot_for(farming_config, Some(50), SlotType::Pill, false),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn test_skill_rotation() {
+        let sim = Simulation::new();
+        let mut image = sim.analyzer("basic/frame_000.png");
+        let mut behavior = sim.behavior();
+        let step = |slot_index: usize| json!({ "slot_bar_index": 0, "slot_index": slot_index });
+        let mut config = serde_json::to_value(config_with(json!({
+            "skill_rotation": {
+                "opener": [step(1)],
+                "filler": [step(2), step(5), step(3)],
+                "finisher": [step(4)],
+                "finisher_target_hp": 30,
+            },
+        })))
+        .unwrap();
+        for slot_index in 1..5 {
+            config["farming_config"]["slot_bars"][0]["slots"][slot_index] =
+                json!({ "slot_type": "AttackSkill", "slot_cooldown": 1000, "slot_enabled": true });
+        }
+        config["farming_config"]["slot_bars"][0]["slots"][5] =
+            json!({ "slot_type": "Flying", "slot_enabled": true });
+        let config: BotConfig = serde_json::from_value(config).unwrap();
+        sim.set_stats(&mut image, 100, 100, 100);
+        sim.engage(&mut behavior, &config, &mut image);
+
+        // The target marker is in sight, no obstacle avoidance
+        let mut image = sim.analyzer("basic/frame_001.png");
+        sim.set_stats(&mut image, 100, 100, 100);
+        let slot =
+            |slot_index: &str| vec![key("F1", KeyMode::Press), key(slot_index, KeyMode::Press)];
+
+        // Opener, then the filler skipping the fly slot
+        for expected in ["1", "2", "3"] {
+            assert_eq!(sim.tick(&mut behavior, &config, &mut image), slot(expected));
+        }
+        // The next filler skill is on cooldown, the rotation waits for it
+        assert_eq!(sim.tick(&mut behavior, &config, &mut image), vec![]);
+
+        sim.set_stats(&mut image, 100, 20, 100);
+        assert_eq!(sim.tick(&mut behavior, &config, &mut image), slot("4"));
+        sim.clock.advance(Duration::from_millis(1001));
+        assert_eq!(sim.tick(&mut behavior, &config, &mut image), slot("4"));
+        assert_eq!(
+            sim.input.take_slots(),
+            vec![(0, 1), (0, 2), (0, 3), (0, 4), (0, 4)]
+        );
+    }
+}
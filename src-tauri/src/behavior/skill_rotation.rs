@@ -0,0 +1,143 @@
+use crate::ipc::{RotationPhase, RotationSlot, SkillRotation};
+
+/// Position of the bot in a `SkillRotation` during a fight.
+#[derive(Debug, Clone, Copy)]
+pub struct RotationProgress {
+    phase: RotationPhase,
+    step: usize,
+}
+
+impl Default for RotationProgress {
+    fn default() -> Self {
+        Self {
+            phase: RotationPhase::Opener,
+            step: 0,
+        }
+    }
+}
+
+impl RotationProgress {
+    /// A new target is engaged, start over with the opener
+    pub fn restart(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Slot of the current step, `None` when the rotation has nothing to use at this point, e.g.
+    /// the opener is over and there is no filler.
+    pub fn current(&mut self, rotation: &SkillRotation, target_hp: u32) -> Option<RotationSlot> {
+        let has_finisher = !rotation.steps(RotationPhase::Finisher).is_empty();
+        if self.phase != RotationPhase::Finisher
+            && has_finisher
+            && target_hp <= rotation.finisher_target_hp()
+        {
+            self.phase = RotationPhase::Finisher;
+            self.step = 0;
+        }
+        if self.phase == RotationPhase::Opener
+            && self.step >= rotation.steps(RotationPhase::Opener).len()
+        {
+            self.phase = RotationPhase::Filler;
+            self.step = 0;
+        }
+
+        let steps = rotation.steps(self.phase);
+        if steps.is_empty() {
+            return None;
+        }
+        // Filler and finisher loop
+        self.step %= steps.len();
+        Some(steps[self.step])
+    }
+
+    /// The current step was used or skipped
+    pub fn advance(&mut self) {
+        self.step += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::RotationProgress;
+    use crate::ipc::{RotationPhase, RotationSlot, SkillRotation};
+
+    fn slot(slot_index: usize) -> RotationSlot {
+        RotationSlot {
+            slot_bar_index: 0,
+            slot_index,
+        }
+    }
+
+    fn rotation(opener: &[usize], filler: &[usize], finisher: &[usize]) -> SkillRotation {
+        let steps = |slots: &[usize]| {
+            slots
+                .iter()
+                .map(|slot_index| json!({ "slot_bar_index": 0, "slot_index": slot_index }))
+                .collect::<Vec<_>>()
+        };
+        serde_json::from_value(json!({
+            "opener": steps(opener),
+            "filler": steps(filler),
+            "finisher": steps(finisher),
+            "finisher_target_hp": 30,
+        }))
+        .unwrap()
+    }
+
+    /// Use `count` steps at `target_hp` and return their slots
+    fn run(
+        progress: &mut RotationProgress,
+        rotation: &SkillRotation,
+        target_hp: u32,
+        count: usize,
+    ) -> Vec<Option<RotationSlot>> {
+        (0..count)
+            .map(|_| {
+                let current = progress.current(rotation, target_hp);
+                progress.advance();
+                current
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rotation_progress() {
+        let rotation = rotation(&[1, 2], &[3, 4], &[5]);
+        let mut progress = RotationProgress::default();
+
+        // Opener once, then the filler loops
+        assert_eq!(
+            run(&mut progress, &rotation, 100, 5),
+            [1, 2, 3, 4, 3].map(|index| Some(slot(index)))
+        );
+        assert_eq!(progress.phase, RotationPhase::Filler);
+
+        // Low target HP switches to the finisher for good
+        assert_eq!(run(&mut progress, &rotation, 30, 2), vec![Some(slot(5)); 2]);
+        assert_eq!(run(&mut progress, &rotation, 60, 1), vec![Some(slot(5))]);
+
+        // Next fight, a target already low on HP skips the opener
+        progress.restart();
+        assert_eq!(run(&mut progress, &rotation, 20, 1), vec![Some(slot(5))]);
+    }
+
+    #[test]
+    fn test_partial_rotation() {
+        // Nothing to use once the opener is over
+        let opener_only = rotation(&[1], &[], &[]);
+        let mut progress = RotationProgress::default();
+        assert_eq!(
+            run(&mut progress, &opener_only, 10, 2),
+            vec![Some(slot(1)), None]
+        );
+
+        // Without finisher the filler keeps going at low HP
+        let filler_only = rotation(&[], &[3], &[]);
+        progress.restart();
+        assert_eq!(
+            run(&mut progress, &filler_only, 10, 2),
+            vec![Some(slot(3)); 2]
+        );
+    }
+}
@@ -1,21 +1,28 @@
 use std::time::{Duration, Instant};
 
 use slog::Logger;
-use tauri::Window;
 
-use super::Behavior;
+use super::{buff_timers::BuffTimers, Behavior};
 use crate::{
-    image_analyzer::ImageAnalyzer,
-    ipc::{BotConfig, FrontendInfo, SlotType, SupportConfig},
+    image_analyzer::{ActionBarState, ImageAnalyzer},
+    ipc::{BotConfig, FrontendInfo, SlotType, SlotsState, SupportConfig},
     movement::MovementAccessor,
-    platform::send_slot_eval,
+    platform::InputSink,
     play,
+    utils::Clock,
 };
 
 pub struct SupportBehavior<'a> {
     movement: &'a MovementAccessor,
-    window: &'a Window,
+    input: &'a dyn InputSink,
+    clock: &'a dyn Clock,
     slots_usage_last_time: [[Option<Instant>; 10]; 9],
+    slots_usage_count: [[u32; 10]; 9],
+    /// What was seen of the slot bars, e.g. empty slots and cooldowns
+    action_bar: ActionBarState,
+    /// Buffs are cast on the target while the buff area only shows the bot's own, their timers
+    /// alone tell when to recast them
+    buff_timers: BuffTimers,
     last_buff_usage: Instant,
     last_jump_time: Instant,
     avoid_obstacle_direction: String,
@@ -24,13 +31,22 @@ pub struct SupportBehavior<'a> {
 }
 
 impl<'a> Behavior<'a> for SupportBehavior<'a> {
-    fn new(_logger: &'a Logger, movement: &'a MovementAccessor, window: &'a Window) -> Self {
+    fn new(
+        _logger: &'a Logger,
+        movement: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self {
         Self {
             movement,
-            window,
+            input,
+            clock,
             slots_usage_last_time: [[None; 10]; 9],
-            last_buff_usage: Instant::now(),
-            last_jump_time: Instant::now(),
+            slots_usage_count: [[0; 10]; 9],
+            action_bar: ActionBarState::default(),
+            buff_timers: BuffTimers::default(),
+            last_buff_usage: clock.now(),
+            last_jump_time: clock.now(),
             avoid_obstacle_direction: "D".to_owned(),
             last_far_from_target: None,
             //is_on_flight: false,
@@ -38,36 +54,44 @@ impl<'a> Behavior<'a> for SupportBehavior<'a> {
     }
 
     fn start(&mut self, _config: &BotConfig) {}
-    fn update(&mut self, _config: &BotConfig) {}
+    fn update(&mut self, _config: &BotConfig) {
+        // Slots may have been reassigned
+        self.action_bar.reset();
+    }
     fn stop(&mut self, _config: &BotConfig) {
         self.slots_usage_last_time = [[None; 10]; 9];
+        self.buff_timers.reset();
     }
 
     fn run_iteration(
         &mut self,
-        _frontend_info: &mut FrontendInfo,
+        frontend_info: &mut FrontendInfo,
         config: &BotConfig,
         image: &mut ImageAnalyzer,
     ) {
         let config = config.support_config();
         let target_marker = image.identify_target_marker(true);
         self.update_slots_usage(config);
+        frontend_info.set_slots_usage(&config.slot_bars(), self.slots_usage_count);
 
         if image.client_stats.target_hp.value == 0 && target_marker.is_some() {
             self.get_slot_for(config, None, SlotType::RezSkill, true);
             self.slots_usage_last_time = [[None; 10]; 9];
+            // Buffs of the target are gone with it
+            self.buff_timers.reset();
             return;
         }
 
+        self.update_action_bar(config, image);
         self.check_restorations(config, image);
-        std::thread::sleep(Duration::from_millis(100));
+        self.clock.sleep(Duration::from_millis(100));
 
         if image.client_stats.target_hp.value > 0 {
             if let Some(target_marker) = target_marker {
                 let marker_distance = image.get_target_marker_distance(target_marker);
                 if marker_distance > 200 {
                     if self.last_far_from_target.is_none() {
-                        self.last_far_from_target = Some(Instant::now());
+                        self.last_far_from_target = Some(self.clock.now());
                     }
                     self.avoid_obstacle(config);
                 } else {
@@ -82,9 +106,21 @@ impl<'a> Behavior<'a> for SupportBehavior<'a> {
 }
 
 impl SupportBehavior<'_> {
+    /// Forget how often slots were used, e.g. once the user refilled them
+    pub fn reset_slots_usage(&mut self) {
+        self.slots_usage_count = [[0; 10]; 9];
+    }
+
+    /// Forget which buffs are active, e.g. they were lost on death
+    pub fn reset_buff_timers(&mut self) {
+        self.buff_timers.reset();
+    }
+
     fn avoid_obstacle(&mut self, config: &SupportConfig) {
         if let Some(last_far_from_target) = self.last_far_from_target {
-            if last_far_from_target.elapsed().as_millis() > config.obstacle_avoidance_cooldown() {
+            if self.clock.elapsed(last_far_from_target).as_millis()
+                > config.obstacle_avoidance_cooldown()
+            {
                 self.move_circle_pattern();
             }
         } else {
@@ -118,24 +154,25 @@ impl SupportBehavior<'_> {
         }
     }
 
+    /// Remember what the visible slot bar looks like
+    fn update_action_bar(&mut self, config: &SupportConfig, image: &ImageAnalyzer) {
+        if !config.empty_slots_detection() && !config.cooldown_detection() {
+            self.action_bar.reset();
+            return;
+        }
+        if let Some(appearances) = image.action_bar_slots() {
+            self.action_bar.update(appearances);
+        }
+    }
+
     /// Update slots cooldown timers
     fn update_slots_usage(&mut self, config: &SupportConfig) {
-        for (slotbar_index, slot_bars) in self.slots_usage_last_time.into_iter().enumerate() {
-            for (slot_index, last_time) in slot_bars.into_iter().enumerate() {
-                let cooldown = config
-                    .get_slot_cooldown(slotbar_index, slot_index)
-                    .unwrap_or(100)
-                    .try_into();
-                if let Some(last_time) = last_time {
-                    if let Ok(cooldown) = cooldown {
-                        let slot_last_time = last_time.elapsed().as_millis();
-                        if slot_last_time > cooldown {
-                            self.slots_usage_last_time[slotbar_index][slot_index] = None;
-                        }
-                    }
-                }
-            }
-        }
+        self.action_bar.release_ready_slots(
+            &config.slot_bars(),
+            &mut self.slots_usage_last_time,
+            config.cooldown_detection(),
+            self.clock.now(),
+        );
     }
 
     fn get_slot_for(
@@ -145,15 +182,22 @@ impl SupportBehavior<'_> {
         slot_type: SlotType,
         send: bool,
     ) -> Option<(usize, usize)> {
-        if let Some(slot_index) =
-            config.get_usable_slot_index(slot_type, threshold, self.slots_usage_last_time)
-        {
+        let slots_state = SlotsState {
+            last_usage: self.slots_usage_last_time,
+            usage_count: self.slots_usage_count,
+            empty: if config.empty_slots_detection() {
+                self.action_bar.empty_slots()
+            } else {
+                [[false; 10]; 9]
+            },
+        };
+        if let Some(slot_index) = config.get_usable_slot_index(slot_type, threshold, &slots_state) {
             if send {
                 //slog::debug!(self.logger, "Slot usage"; "slot_type" => slot_type.to_string(), "value" => threshold);
                 self.send_slot(slot_index);
                 if slot_type == SlotType::HealSkill {
                     // Set last buff usage to now so that a buff doesn't try and happen immediately after a heal
-                    self.last_buff_usage = Instant::now();
+                    self.last_buff_usage = self.clock.now();
                 }
             }
 
@@ -164,16 +208,35 @@ impl SupportBehavior<'_> {
 
     fn send_slot(&mut self, slot_index: (usize, usize)) {
         // Send keystroke for first slot mapped to pill
-        send_slot_eval(self.window, slot_index.0, slot_index.1);
+        self.input.send_slot(slot_index.0, slot_index.1);
+        self.action_bar.set_visible_slot_bar(slot_index.0);
         // Update usage last time
-        self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(Instant::now());
+        self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(self.clock.now());
+        self.slots_usage_count[slot_index.0][slot_index.1] += 1;
     }
 
     fn check_buffs(&mut self, config: &SupportConfig) {
-        if self.last_buff_usage.elapsed().as_millis() > config.interval_between_buffs() {
-            self.last_buff_usage = Instant::now();
+        // Buffs with a duration are recast right before they lapse
+        let now = self.clock.now();
+        let slot_bars = config.slot_bars();
+        let recast = self
+            .buff_timers
+            .next_recast(&slot_bars, now, |slot_index, slot| {
+                slot.is_enabled()
+                    && self.slots_usage_last_time[slot_index.0][slot_index.1].is_none()
+                    && !slot.is_exhausted(self.slots_usage_count[slot_index.0][slot_index.1])
+            });
+        if let Some(slot_index) = recast {
+            self.send_slot(slot_index);
+            let slot = slot_bars[slot_index.0].slots()[slot_index.1];
+            self.buff_timers.record_cast(&slot, slot_index, now);
+            self.clock.sleep(Duration::from_millis(100));
+        } else if self.clock.elapsed(self.last_buff_usage).as_millis()
+            > config.interval_between_buffs()
+        {
+            self.last_buff_usage = self.clock.now();
             self.get_slot_for(config, None, SlotType::BuffSkill, true);
-            std::thread::sleep(Duration::from_millis(100));
+            self.clock.sleep(Duration::from_millis(100));
         }
     }
 
@@ -207,3 +270,96 @@ impl SupportBehavior<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc, time::Duration};
+
+    use image::Rgba;
+    use serde_json::json;
+    use slog::Logger;
+
+    use super::SupportBehavior;
+    use crate::{
+        behavior::Behavior,
+        image_analyzer::{FrameSource, ImageAnalyzer},
+        ipc::{BotConfig, FrontendInfo},
+        movement::MovementAccessor,
+        platform::RecordingInputSink,
+        utils::{Clock, ManualClock},
+    };
+
+    #[test]
+    fn test_target_buffs_ignore_buff_area() {
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let input = Arc::new(RecordingInputSink::new());
+        let clock = Arc::new(ManualClock::new());
+        let movement = MovementAccessor::new(input.clone(), clock.clone());
+
+        // A timed buff, the icon fields are left over from configs where support had detection
+        let unused = json!({ "slot_type": "Unused", "slot_enabled": false });
+        let mut slot_bars = vec![json!({ "slots": vec![unused; 10] }); 9];
+        slot_bars[0]["slots"][0] = json!({
+            "slot_type": "BuffSkill",
+            "slot_cooldown": 1000,
+            "slot_buff_duration": 60_000,
+            "slot_enabled": true,
+        });
+        let config: BotConfig = serde_json::from_value(json!({
+            "change_id": 0,
+            "is_running": true,
+            "mode": "Support",
+            "farming_config": {},
+            "support_config": {
+                "slot_bars": slot_bars,
+                "buff_detection": true,
+                "buff_icons": [{
+                    "slot_bar_index": 0,
+                    "slot_index": 0,
+                    "icon": { "width": 1, "height": 1, "pixels": [[255, 0, 255]] },
+                }],
+            },
+            "shout_config": {},
+        }))
+        .unwrap();
+
+        // Party member targeted next to the bot, the buff area of the frame shows no icon
+        let fixture =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/frames/basic/frame_001.png");
+        let mut frame = image::open(fixture).unwrap().to_rgba8();
+        for x in 424..=436 {
+            for y in 236..=242 {
+                frame.put_pixel(x, y, Rgba([164, 180, 226, 255]));
+            }
+        }
+        let frame_path =
+            std::env::temp_dir().join(format!("neuz_support_{}.png", std::process::id()));
+        frame.save(&frame_path).unwrap();
+        let mut image = ImageAnalyzer::from_frame_source(
+            FrameSource::from_path(&frame_path).unwrap(),
+            Some(input.clone()),
+        );
+        image.capture_window(&logger, config.farming_config());
+        std::fs::remove_file(frame_path).unwrap();
+        for stat in [
+            &mut image.client_stats.target_hp,
+            &mut image.client_stats.hp,
+        ] {
+            stat.value = 100;
+            stat.reset_last_update_time(clock.now());
+        }
+
+        let mut behavior = SupportBehavior::new(&logger, &movement, &*input, &*clock);
+        behavior.run_iteration(&mut FrontendInfo::default(), &config, &mut image);
+        assert_eq!(input.take_slots(), vec![(0, 0)]);
+
+        // The buff is on the target, not missing
+        clock.advance(Duration::from_secs(10));
+        behavior.run_iteration(&mut FrontendInfo::default(), &config, &mut image);
+        assert_eq!(input.take_slots(), vec![]);
+
+        clock.advance(Duration::from_secs(50));
+        behavior.run_iteration(&mut FrontendInfo::default(), &config, &mut image);
+        assert_eq!(input.take_slots(), vec![(0, 0)]);
+    }
+}
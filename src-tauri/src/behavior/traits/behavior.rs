@@ -1,16 +1,21 @@
 use slog::Logger;
-use tauri::Window;
 
 use crate::{
     image_analyzer::ImageAnalyzer,
     ipc::{BotConfig, FrontendInfo},
     movement::MovementAccessor,
+    platform::InputSink,
+    utils::Clock,
 };
 
 pub trait Behavior<'a> {
     /// Runs on initialization
-    fn new(logger: &'a Logger, movement_accessor: &'a MovementAccessor, window: &'a Window)
-        -> Self;
+    fn new(
+        logger: &'a Logger,
+        movement_accessor: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self;
 
     /// Runs on activation
     fn start(&mut self, config: &BotConfig);
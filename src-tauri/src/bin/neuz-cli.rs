@@ -0,0 +1,241 @@
+//! Run a behavior against frames from disk instead of the game client, e.g. to tune
+//! `FarmingConfig` thresholds on a recorded session.
+//!
+//! Prints state transitions and emitted inputs as JSON lines on stdout.
+
+use std::{env, path::PathBuf, process, sync::Arc, time::Duration};
+
+use app::{
+    behavior::{Behavior, FarmingBehavior, ShoutBehavior, SupportBehavior},
+    image_analyzer::{FrameSource, ImageAnalyzer},
+    ipc::{BotConfig, BotMode, FrontendInfo},
+    movement::MovementAccessor,
+    platform::{InputSink, RecordingInputSink},
+    recorder::StatsSnapshot,
+    utils::{metrics, Clock, ManualClock},
+};
+use serde_json::json;
+use slog::{Drain, Logger};
+
+const USAGE: &str = "usage: neuz-cli --config <bot_config.json> --frames <dir|frame.png> \
+[--mode farming|support|auto_shout] [--tick-ms <ms>] [--ticks <count>] [--seed <seed>] \
+[--metrics <metrics.json>] [--verbose]";
+
+/// Ticks simulated for a single frame file, which never runs out
+const SINGLE_FRAME_TICKS: u64 = 100;
+
+struct Args {
+    config: PathBuf,
+    frames: PathBuf,
+    /// Overrides the mode of the config
+    mode: Option<BotMode>,
+    tick_duration: Duration,
+    ticks: Option<u64>,
+    seed: Option<u64>,
+    /// Where to write the timing metrics of the run
+    metrics: Option<PathBuf>,
+    verbose: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut config = None;
+        let mut frames = None;
+        let mut parsed = Self {
+            config: PathBuf::new(),
+            frames: PathBuf::new(),
+            mode: None,
+            tick_duration: Duration::from_millis(100),
+            ticks: None,
+            seed: None,
+            metrics: None,
+            verbose: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--config" => config = Some(PathBuf::from(value()?)),
+                "--frames" => frames = Some(PathBuf::from(value()?)),
+                "--mode" => parsed.mode = Some(parse_mode(&value()?)?),
+                "--tick-ms" => {
+                    parsed.tick_duration = Duration::from_millis(parse_number(&value()?)?)
+                }
+                "--ticks" => parsed.ticks = Some(parse_number(&value()?)?),
+                "--seed" => parsed.seed = Some(parse_number(&value()?)?),
+                "--metrics" => parsed.metrics = Some(PathBuf::from(value()?)),
+                "--verbose" => parsed.verbose = true,
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+
+        parsed.config = config.ok_or("--config is required")?;
+        parsed.frames = frames.ok_or("--frames is required")?;
+        Ok(parsed)
+    }
+}
+
+fn parse_mode(mode: &str) -> Result<BotMode, String> {
+    [BotMode::Farming, BotMode::Support, BotMode::AutoShout]
+        .into_iter()
+        .find(|candidate| candidate.to_string() == mode)
+        .ok_or(format!("unknown mode {}", mode))
+}
+
+fn parse_number(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} is not a number", value))
+}
+
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("neuz-cli: {}\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(args) {
+        eprintln!("neuz-cli: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let logger = if args.verbose {
+        let decorator = slog_term::PlainSyncDecorator::new(std::io::stderr());
+        Logger::root(
+            slog_term::FullFormat::new(decorator).build().fuse(),
+            slog::o!(),
+        )
+    } else {
+        Logger::root(slog::Discard, slog::o!())
+    };
+
+    let config = BotConfig::load(&args.config).map_err(|err| err.to_string())?;
+    let mode = args
+        .mode
+        .or_else(|| config.mode())
+        .ok_or("the config has no mode, pass --mode")?;
+    let max_ticks = args.ticks.or_else(|| {
+        if args.frames.is_file() {
+            Some(SINGLE_FRAME_TICKS)
+        } else {
+            None
+        }
+    });
+
+    let frame_source = FrameSource::from_path(&args.frames)
+        .map_err(|err| format!("{}: {}", args.frames.display(), err))?;
+    let clock = Arc::new(ManualClock::new());
+    let input = Arc::new(RecordingInputSink::new());
+    let movement = MovementAccessor::new(input.clone(), clock.clone());
+    let mut image_analyzer =
+        ImageAnalyzer::from_frame_source(frame_source, Some(input.clone() as Arc<dyn InputSink>));
+
+    let mut farming_behavior = FarmingBehavior::new(&logger, &movement, &*input, &*clock);
+    let mut shout_behavior = ShoutBehavior::new(&logger, &movement, &*input, &*clock);
+    let mut support_behavior = SupportBehavior::new(&logger, &movement, &*input, &*clock);
+    if let Some(seed) = args.seed {
+        farming_behavior.set_rng_seed(seed);
+        movement.set_rng_seed(seed);
+    }
+    match mode {
+        BotMode::Farming => farming_behavior.start(&config),
+        BotMode::Support => support_behavior.start(&config),
+        BotMode::AutoShout => shout_behavior.start(&config),
+    }
+
+    let started = clock.now();
+    let mut frontend_info = FrontendInfo::default();
+    let mut last_state: Option<String> = None;
+    let mut tick = 0;
+    while max_ticks.map_or(true, |max_ticks| tick < max_ticks) {
+        if image_analyzer.frame_source().is_exhausted() {
+            break;
+        }
+        image_analyzer.set_hud_layout(config.hud_layout());
+        image_analyzer.set_status_bar_colors(config.farming_config().status_bar_colors());
+        image_analyzer.capture_window(&logger, config.farming_config());
+        if !image_analyzer.image_is_some() {
+            return Err("no frame could be loaded".to_string());
+        }
+        image_analyzer
+            .client_stats
+            .update(&image_analyzer.clone(), &logger);
+
+        // Like the app, behaviors are paused while the character is dead
+        let state = if image_analyzer.client_stats.is_alive() {
+            match mode {
+                BotMode::Farming => {
+                    farming_behavior.run_iteration(
+                        &mut frontend_info,
+                        &config,
+                        &mut image_analyzer,
+                    );
+                    farming_behavior.state_name()
+                }
+                BotMode::Support => {
+                    support_behavior.run_iteration(
+                        &mut frontend_info,
+                        &config,
+                        &mut image_analyzer,
+                    );
+                    mode.to_string()
+                }
+                BotMode::AutoShout => {
+                    shout_behavior.run_iteration(&mut frontend_info, &config, &mut image_analyzer);
+                    mode.to_string()
+                }
+            }
+        } else {
+            "Dead".to_string()
+        };
+
+        let elapsed_ms = clock.elapsed(started).as_millis();
+        if last_state.as_ref() != Some(&state) {
+            print_line(json!({
+                "tick": tick,
+                "elapsed_ms": elapsed_ms,
+                "type": "transition",
+                "from": last_state,
+                "to": state,
+                "stats": StatsSnapshot::from(&image_analyzer.client_stats),
+            }));
+            last_state = Some(state);
+        }
+        for event in input.take() {
+            print_line(json!({
+                "tick": tick,
+                "elapsed_ms": elapsed_ms,
+                "type": "input",
+                "input": event,
+            }));
+        }
+
+        if mode == BotMode::Farming && farming_behavior.is_mobs_timeout_reached() {
+            print_line(json!({
+                "tick": tick,
+                "elapsed_ms": elapsed_ms,
+                "type": "exit",
+                "reason": "mobs_timeout",
+            }));
+            break;
+        }
+
+        tick += 1;
+        clock.advance(args.tick_duration);
+    }
+
+    if let Some(path) = &args.metrics {
+        metrics::global()
+            .dump(path)
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    Ok(())
+}
+
+fn print_line(line: serde_json::Value) {
+    println!("{}", line);
+}
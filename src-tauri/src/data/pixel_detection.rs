@@ -112,7 +112,7 @@ impl PixelDetection {
     pub fn update_value(&mut self, image: &ImageAnalyzer) {
         let config: PixelDetectionConfig = self.pixel_kind.into();
 
-        let recv = image.pixel_detection(
+        let cloud = image.pixel_detection(
             config.refs,
             config.min_x,
             config.min_y,
@@ -121,15 +121,6 @@ impl PixelDetection {
             Some(10),
         );
 
-        // Receive points from channel
-        let cloud = {
-            let mut cloud = PointCloud::default();
-            while let Ok(point) = recv.recv() {
-                cloud.push(point);
-            }
-            cloud
-        };
-
         let updated_value = !cloud.is_empty();
 
         // Update values if needed
@@ -79,7 +79,7 @@ impl PointCloud {
         let mut clusters = vec![PointCloud::default()];
 
         // Iterate over points and build clusters
-        for point in points {
+        for point in points.points {
             // Fill default cluster first
             let last_cluster = clusters.last_mut().unwrap();
             if last_cluster.is_empty() {
@@ -106,6 +106,108 @@ impl PointCloud {
             _ => clusters,
         }
     }
+
+    /// Split the cloud into 8-connected components after dilating every point by `dilate_x`
+    /// pixels horizontally and `dilate_y` pixels vertically, i.e. points are connected when their
+    /// gap is at most `2 * dilate + 1` pixels on both axes.
+    ///
+    /// Components are ordered by their first point in reading order (top to bottom, left to
+    /// right), points keep their order in the cloud.
+    pub fn connected_components(&self, dilate_x: u32, dilate_y: u32) -> Vec<PointCloud> {
+        if self.points.is_empty() {
+            return Vec::default();
+        }
+
+        // Binary mask over the cloud bounds holding the index of the point on each pixel
+        let bounds = self.to_bounds();
+        let (width, height) = (bounds.w as usize + 1, bounds.h as usize + 1);
+        let mut mask = vec![usize::MAX; width * height];
+        let mask_index = |x: u32, y: u32| (y - bounds.y) as usize * width + (x - bounds.x) as usize;
+        for (index, point) in self.points.iter().enumerate() {
+            mask[mask_index(point.x, point.y)] = index;
+        }
+
+        // Union each point with every point of the forward half of its neighborhood, the other
+        // half is covered by the neighbors themselves
+        let reach_x = dilate_x.saturating_mul(2).saturating_add(1);
+        let reach_y = dilate_y.saturating_mul(2).saturating_add(1);
+        let mut components = DisjointSet::new(self.points.len());
+        for (index, point) in self.points.iter().enumerate() {
+            let max_y = point.y.saturating_add(reach_y).min(bounds.y + bounds.h);
+            let min_x = point.x.saturating_sub(reach_x).max(bounds.x);
+            let max_x = point.x.saturating_add(reach_x).min(bounds.x + bounds.w);
+            for y in point.y..=max_y {
+                let start_x = if y == point.y { point.x + 1 } else { min_x };
+                for x in start_x..=max_x {
+                    let neighbor = mask[mask_index(x, y)];
+                    if neighbor != usize::MAX {
+                        components.union(index, neighbor);
+                    }
+                }
+            }
+        }
+
+        // Duplicate points all map to the same mask pixel, keep them with their twin
+        let mut roots = Vec::with_capacity(self.points.len());
+        for point in &self.points {
+            roots.push(components.find(mask[mask_index(point.x, point.y)]));
+        }
+
+        let mut clouds: Vec<(usize, PointCloud)> = Vec::default();
+        let mut cloud_by_root = vec![usize::MAX; self.points.len()];
+        let mut order: Vec<usize> = (0..self.points.len()).collect();
+        order.sort_by_key(|&index| (self.points[index].y, self.points[index].x, index));
+        for index in order {
+            let root = roots[index];
+            if cloud_by_root[root] == usize::MAX {
+                cloud_by_root[root] = clouds.len();
+                clouds.push((index, PointCloud::default()));
+            }
+        }
+        for (index, point) in self.points.iter().enumerate() {
+            clouds[cloud_by_root[roots[index]]].1.push(*point);
+        }
+
+        clouds.into_iter().map(|(_, cloud)| cloud).collect()
+    }
+}
+
+/// Union-find with path halving and union by rank.
+struct DisjointSet {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl DisjointSet {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+        }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parent[index] != index {
+            self.parent[index] = self.parent[self.parent[index]];
+            index = self.parent[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        match self.rank[a].cmp(&self.rank[b]) {
+            std::cmp::Ordering::Less => self.parent[a] = b,
+            std::cmp::Ordering::Greater => self.parent[b] = a,
+            std::cmp::Ordering::Equal => {
+                self.parent[b] = a;
+                self.rank[a] += 1;
+            }
+        }
+    }
 }
 
 impl AsRef<[Point]> for PointCloud {
@@ -155,6 +257,41 @@ mod tests {
         assert_eq!(clusters[1], PointCloud::from([(15, 5), (17, 3)]));
     }
 
+    #[test]
+    fn test_connected_components() {
+        // Two words of a nameplate, a second nameplate right below and a stray pixel
+        let mut points = vec![];
+        for x in (10..20).chain(24..30) {
+            points.push((x, 10));
+            points.push((x, 12));
+        }
+        points.extend((12..26).map(|x| (x, 20)));
+        points.push((60, 10));
+        points.push((60, 10));
+        let cloud = PointCloud::from(points);
+
+        let bounds = cloud
+            .connected_components(2, 1)
+            .iter()
+            .map(PointCloud::to_bounds)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bounds,
+            vec![
+                Bounds::new(10, 10, 19, 2),
+                Bounds::new(60, 10, 0, 0),
+                Bounds::new(12, 20, 13, 0),
+            ]
+        );
+
+        // Without dilation the words and rows fall apart
+        assert_eq!(cloud.connected_components(0, 0).len(), 6);
+        assert!(PointCloud::default().connected_components(2, 1).is_empty());
+
+        // Huge dilations merge everything instead of overflowing
+        assert_eq!(cloud.connected_components(u32::MAX, u32::MAX).len(), 1);
+    }
+
     #[test]
     fn test_approx_rect() {
         let bounds = PointCloud::from([(0, 0), (10, 10)]).to_bounds();
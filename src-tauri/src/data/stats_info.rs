@@ -1,15 +1,16 @@
-use std::{fmt, time::Instant};
+use std::{fmt, sync::Arc, time::Instant};
 
+use serde::{Deserialize, Serialize};
 use slog::Logger;
-use tauri::Window;
 
-use super::PointCloud;
+use super::{Bounds, PointCloud};
 use crate::{
-    image_analyzer::{Color, ImageAnalyzer},
-    platform::{eval_send_key, KeyMode},
+    image_analyzer::{Color, Detection, ImageAnalyzer},
+    ipc::{HudLayout, HudRegion, StatusBarColors},
+    platform::{InputSink, KeyMode},
 };
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StatusBarKind {
     #[default]
     Hp,
@@ -39,10 +40,10 @@ pub struct ClientStats {
     pub target_mp: StatInfo,
     is_alive: bool,
     pub stat_try_not_detected_count: i32,
-    window: Window,
+    input: Option<Arc<dyn InputSink>>,
 }
 impl ClientStats {
-    pub fn new(window: Window) -> Self {
+    pub fn new(input: Option<Arc<dyn InputSink>>) -> Self {
         Self {
             hp: StatInfo::new(0, 100, StatusBarKind::Hp, None),
             mp: StatInfo::new(0, 100, StatusBarKind::Mp, None),
@@ -51,7 +52,7 @@ impl ClientStats {
             target_mp: StatInfo::new(0, 0, StatusBarKind::TargetMP, None),
             is_alive: true,
             stat_try_not_detected_count: 0,
-            window,
+            input,
         }
     }
 
@@ -79,7 +80,9 @@ impl ClientStats {
                 self.stat_try_not_detected_count = 0;
 
                 // Try to open char stat tray
-                eval_send_key(&self.window, "T", KeyMode::Press);
+                if let Some(input) = &self.input {
+                    input.send_key("T", KeyMode::Press);
+                }
             }
             false
         } else {
@@ -88,6 +91,14 @@ impl ClientStats {
         }
     }
 
+    /// Bounds of every bar as detected in the last frame.
+    pub fn bars_bounds(&self) -> Vec<(StatusBarKind, Bounds)> {
+        [self.hp, self.mp, self.fp, self.target_hp, self.target_mp]
+            .iter()
+            .map(|stat| (stat.stat_kind, stat.bounds))
+            .collect()
+    }
+
     // bot died
     pub fn is_alive(&mut self) -> bool {
         // We need to be sure that char tray is open before
@@ -118,6 +129,7 @@ pub struct StatInfo {
     pub stat_kind: StatusBarKind,
     pub last_value: u32,
     pub last_update_time: Option<Instant>,
+    pub bounds: Bounds,
 }
 
 impl PartialEq for StatInfo {
@@ -145,6 +157,7 @@ impl StatInfo {
             stat_kind,
             last_update_time: Some(Instant::now()),
             last_value: 100,
+            bounds: Bounds::default(),
         };
         if let Some(image) = image {
             res.update_value(image);
@@ -153,32 +166,17 @@ impl StatInfo {
         res
     }
 
-    pub fn reset_last_update_time(&mut self) {
-        self.last_update_time = Some(Instant::now());
+    pub fn reset_last_update_time(&mut self, now: Instant) {
+        self.last_update_time = Some(now);
     }
 
     pub fn update_value(&mut self, image: &ImageAnalyzer) -> bool {
-        let status_bar_config: StatusBarConfig = self.stat_kind.into();
-        let recv = image.pixel_detection(
-            status_bar_config.refs,
-            status_bar_config.min_x,
-            status_bar_config.min_y,
-            status_bar_config.max_x,
-            status_bar_config.max_y,
-            Some(2),
-        );
-
-        // Receive points from channel
-        let cloud = {
-            let mut cloud = PointCloud::default();
-            while let Ok(point) = recv.recv() {
-                cloud.push(point);
-            }
-            cloud
-        };
-
-        // Calculate bounds
-        let bounds = cloud.to_bounds();
+        // Calculate bounds from the pixels found while scanning the frame
+        let bounds = image
+            .detected_pixels(Detection::StatusBar(self.stat_kind))
+            .map(PointCloud::to_bounds)
+            .unwrap_or_default();
+        self.bounds = bounds;
 
         // Recalculate value tracking info
         let updated_max_w = bounds.w.max(self.max_w);
@@ -200,6 +198,9 @@ impl StatInfo {
     }
 }
 
+/// Tolerance of the built-in bar palette, the bars have flat colors
+const DEFAULT_BAR_TOLERANCE: u8 = 2;
+
 #[derive(Debug, Clone)]
 pub struct StatusBarConfig {
     pub max_x: u32,
@@ -207,82 +208,73 @@ pub struct StatusBarConfig {
     pub min_x: u32,
     pub min_y: u32,
     pub refs: Vec<Color>,
+    pub tolerance: u8,
 }
 
 impl StatusBarConfig {
-    pub fn new(colors: [[u8; 3]; 4]) -> Self {
+    pub fn new(colors: &[[u8; 3]], tolerance: u8, region: HudRegion) -> Self {
         Self {
+            max_x: region.max_x,
+            max_y: region.max_y,
+            min_x: region.min_x,
+            min_y: region.min_y,
             refs: colors
                 .iter()
                 .map(|v| Color::new(v[0], v[1], v[2]))
                 .collect(),
-            ..Default::default()
+            tolerance,
         }
     }
-}
 
-impl From<StatusBarKind> for StatusBarConfig {
-    fn from(kind: StatusBarKind) -> Self {
+    /// Get the bar colors and the region to look for them in the given HUD layout.
+    /// Calibrated colors and tolerances replace the built-in ones, target bars share the player's
+    /// palette.
+    pub fn from_layout(kind: StatusBarKind, layout: &HudLayout, colors: &StatusBarColors) -> Self {
         use StatusBarKind::*;
 
-        match kind {
-            Hp => {
-                StatusBarConfig::new([[174, 18, 55], [188, 24, 62], [204, 30, 70], [220, 36, 78]])
-            }
+        let hp_colors = colors.hp.as_deref().unwrap_or(&[
+            [174, 18, 55],
+            [188, 24, 62],
+            [204, 30, 70],
+            [220, 36, 78],
+        ]);
+        let mp_colors = colors.mp.as_deref().unwrap_or(&[
+            [20, 84, 196],
+            [36, 132, 220],
+            [44, 164, 228],
+            [56, 188, 232],
+        ]);
+        let fp_colors = colors.fp.as_deref().unwrap_or(&[
+            [45, 230, 29],
+            [28, 172, 28],
+            [44, 124, 52],
+            [20, 146, 20],
+        ]);
+
+        let hp_tolerance = colors.hp_tolerance.unwrap_or(DEFAULT_BAR_TOLERANCE);
+        let mp_tolerance = colors.mp_tolerance.unwrap_or(DEFAULT_BAR_TOLERANCE);
+        let fp_tolerance = colors.fp_tolerance.unwrap_or(DEFAULT_BAR_TOLERANCE);
 
-            Mp => StatusBarConfig::new([
-                [20, 84, 196],
-                [36, 132, 220],
-                [44, 164, 228],
-                [56, 188, 232],
-            ]),
-            Fp => {
-                StatusBarConfig::new([[45, 230, 29], [28, 172, 28], [44, 124, 52], [20, 146, 20]])
-            }
-
-            TargetHP => {
-                let mut target_hp_bar = StatusBarConfig::new([
-                    [174, 18, 55],
-                    [188, 24, 62],
-                    [204, 30, 70],
-                    [220, 36, 78],
-                ]);
-                target_hp_bar.min_x = 300;
-                target_hp_bar.min_y = 30;
-
-                target_hp_bar.max_x = 550;
-                target_hp_bar.max_y = 60;
-
-                target_hp_bar
-            }
-
-            TargetMP => {
-                let mut target_mp_bar = StatusBarConfig::new([
-                    [20, 84, 196],
-                    [36, 132, 220],
-                    [44, 164, 228],
-                    [56, 188, 232],
-                ]);
-                target_mp_bar.min_x = 300;
-                target_mp_bar.min_y = 50;
-
-                target_mp_bar.max_x = 550;
-                target_mp_bar.max_y = 60;
-
-                target_mp_bar
-            }
+        match kind {
+            Hp => StatusBarConfig::new(hp_colors, hp_tolerance, layout.stat_bars),
+            Mp => StatusBarConfig::new(mp_colors, mp_tolerance, layout.stat_bars),
+            Fp => StatusBarConfig::new(fp_colors, fp_tolerance, layout.stat_bars),
+            TargetHP => StatusBarConfig::new(hp_colors, hp_tolerance, layout.target_hp_bar),
+            TargetMP => StatusBarConfig::new(mp_colors, mp_tolerance, layout.target_mp_bar),
         }
     }
 }
 
 impl Default for StatusBarConfig {
     fn default() -> Self {
+        let region = HudLayout::default().stat_bars;
         Self {
-            max_x: 225,
-            max_y: 110,
-            min_x: 105,
-            min_y: 30,
+            max_x: region.max_x,
+            max_y: region.max_y,
+            min_x: region.min_x,
+            min_y: region.min_y,
             refs: vec![],
+            tolerance: DEFAULT_BAR_TOLERANCE,
         }
     }
 }
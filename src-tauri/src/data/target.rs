@@ -1,12 +1,14 @@
+use serde::{Deserialize, Serialize};
+
 use super::{Bounds, Point};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MobType {
     Passive,
     Aggressive,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum TargetType {
     Mob(MobType),
     #[default]
@@ -14,7 +16,7 @@ pub enum TargetType {
 }
 
 /// A target in 2D space.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Target {
     pub target_type: TargetType,
     pub bounds: Bounds,
@@ -1,21 +1,33 @@
-use std::{
-    sync::mpsc::{sync_channel, Receiver},
-    time::Instant,
-};
+use std::{sync::Arc, time::Instant};
 
 //use libscreenshot::shared::Area;
-use libscreenshot::{ImageBuffer, WindowCaptureProvider};
-use rayon::iter::{ParallelBridge, ParallelIterator};
+use libscreenshot::ImageBuffer;
 use slog::Logger;
 use tauri::Window;
 
 use crate::{
-    data::{point_selector, Bounds, ClientStats, MobType, Point, PointCloud, Target, TargetType},
-    ipc::FarmingConfig,
-    platform::{IGNORE_AREA_BOTTOM, IGNORE_AREA_TOP},
+    data::{
+        point_selector, Bounds, ClientStats, MobType, PointCloud, StatusBarConfig, StatusBarKind,
+        Target, TargetType,
+    },
+    ipc::{FarmingConfig, HudLayout, StatusBarColors},
+    platform::{self, InputSink},
     utils::Timer,
 };
 
+mod action_bar;
+mod buff_icons;
+mod calibration;
+mod frame_source;
+mod scanner;
+pub use self::{
+    action_bar::{ActionBarState, SlotAppearance, ACTION_BAR_SLOTS},
+    buff_icons::IconTemplate,
+    calibration::{CalibrationTarget, ColorCalibration},
+    frame_source::{FrameError, FrameSource},
+    scanner::{Detector, FrameScan, FrameScanner},
+};
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Color {
     pub refs: [u8; 3],
@@ -27,100 +39,193 @@ impl Color {
     }
 }
 
+/// What a detector of the frame scan looks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    PassiveMobs,
+    AggressiveMobs,
+    TargetMarker,
+    BlankTargetMarker,
+    StatusBar(StatusBarKind),
+}
+
 #[derive(Debug, Clone)]
 pub struct ImageAnalyzer {
     image: Option<ImageBuffer>,
-    pub window_id: u64,
+    scan: Option<FrameScan<Detection>>,
+    frame_source: FrameSource,
+    hud_layout: HudLayout,
+    status_bar_colors: StatusBarColors,
     pub client_stats: ClientStats,
 }
 
 impl ImageAnalyzer {
     pub fn new(window: &Window) -> Self {
         Self {
-            window_id: 0,
             image: None,
-            client_stats: ClientStats::new(window.to_owned()),
+            scan: None,
+            frame_source: FrameSource::Window(platform::get_window_id(window).unwrap_or(0)),
+            hud_layout: HudLayout::default(),
+            status_bar_colors: StatusBarColors::default(),
+            client_stats: ClientStats::new(Some(Arc::new(window.to_owned()))),
+        }
+    }
+
+    /// Create an analyzer that isn't attached to a client window, e.g. to replay frames from disk.
+    pub fn from_frame_source(frame_source: FrameSource, input: Option<Arc<dyn InputSink>>) -> Self {
+        Self {
+            image: None,
+            scan: None,
+            frame_source,
+            hud_layout: HudLayout::default(),
+            status_bar_colors: StatusBarColors::default(),
+            client_stats: ClientStats::new(input),
+        }
+    }
+
+    pub fn frame_source(&self) -> &FrameSource {
+        &self.frame_source
+    }
+
+    pub fn set_hud_layout(&mut self, hud_layout: HudLayout) {
+        self.hud_layout = hud_layout;
+    }
+
+    /// Get the configured HUD layout scaled to the current frame.
+    pub fn hud_layout(&self) -> HudLayout {
+        match self.image.as_ref() {
+            Some(image) => self.hud_layout.scaled_to(image.width(), image.height()),
+            None => self.hud_layout.clone(),
         }
     }
 
+    pub fn set_status_bar_colors(&mut self, status_bar_colors: StatusBarColors) {
+        self.status_bar_colors = status_bar_colors;
+    }
+
+    pub fn status_bar_colors(&self) -> &StatusBarColors {
+        &self.status_bar_colors
+    }
+
+    pub fn image(&self) -> Option<&ImageBuffer> {
+        self.image.as_ref()
+    }
+
+    /// Size of the current frame.
+    pub fn image_size(&self) -> Option<(u32, u32)> {
+        self.image
+            .as_ref()
+            .map(|image| (image.width(), image.height()))
+    }
+
     pub fn image_is_some(&self) -> bool {
         self.image.is_some()
     }
 
-    pub fn capture_window(&mut self, logger: &Logger, _config: &FarmingConfig) {
+    /// Capture the next frame and scan it for mobs, target markers and status bars, using the
+    /// colors of `config` and the current HUD layout and status bar colors.
+    pub fn capture_window(&mut self, logger: &Logger, config: &FarmingConfig) {
         let _timer = Timer::start_new("capture_window");
-        if self.window_id == 0 {
-            return;
-        }
-
-        if let Some(provider) = libscreenshot::get_window_capture_provider() {
-            if let Ok(image) = provider.capture_window(self.window_id) {
+        match self.frame_source.next_frame() {
+            Ok(image) => {
                 self.image = Some(image);
-            } else {
-                slog::warn!(logger, "Failed to capture window"; "window_id" => self.window_id);
+                self.scan_frame(config);
             }
+            Err(FrameError::Unavailable) => {}
+            Err(err) => {
+                slog::warn!(logger, "Failed to capture frame"; "source" => self.frame_source.to_string(), "error" => err.to_string());
+            }
+        }
+    }
+
+    fn scan_frame(&mut self, config: &FarmingConfig) {
+        let _timer = Timer::start_new("scan_frame");
+        let scanner = self.frame_scanner(config);
+        if let Some(image) = self.image.as_ref() {
+            self.scan = Some(scanner.scan(image, self.hud_layout().ignore_area_bottom));
         }
     }
 
+    /// Detectors run on every captured frame.
+    pub fn frame_scanner(&self, config: &FarmingConfig) -> FrameScanner<Detection> {
+        let hud_layout = self.hud_layout();
+        let mut scanner = FrameScanner::default();
+
+        // Reference colors
+        let ref_color_pas_wrapped: [Option<u8>; 3] = config.passive_mobs_colors(); // Passive mobs 234, 234, 149
+        let ref_color_agg_wrapped: [Option<u8>; 3] = config.aggressive_mobs_colors(); // Aggro mobs 179, 23, 23
+        let ref_color_pas = Color::new(
+            ref_color_pas_wrapped[0].unwrap_or(234),
+            ref_color_pas_wrapped[1].unwrap_or(234),
+            ref_color_pas_wrapped[2].unwrap_or(149),
+        );
+        let ref_color_agg = Color::new(
+            ref_color_agg_wrapped[0].unwrap_or(179),
+            ref_color_agg_wrapped[1].unwrap_or(23),
+            ref_color_agg_wrapped[2].unwrap_or(23),
+        );
+
+        // avoid detect the health bar as a monster
+        scanner.register(
+            Detection::PassiveMobs,
+            Detector::new(vec![ref_color_pas], config.passive_tolerence())
+                .excluding(hud_layout.stat_tray)
+                .downscaled(config.mobs_scan_downscale()),
+        );
+        scanner.register(
+            Detection::AggressiveMobs,
+            Detector::new(vec![ref_color_agg], config.aggressive_tolerence())
+                .excluding(hud_layout.stat_tray)
+                .downscaled(config.mobs_scan_downscale()),
+        );
+        scanner.register(
+            Detection::TargetMarker,
+            Detector::new(vec![Color::new(246, 90, 106)], 5),
+        );
+        scanner.register(
+            Detection::BlankTargetMarker,
+            Detector::new(vec![Color::new(164, 180, 226)], 5),
+        );
+
+        use StatusBarKind::*;
+        for kind in [Hp, Mp, Fp, TargetHP, TargetMP] {
+            let bar = StatusBarConfig::from_layout(kind, &hud_layout, &self.status_bar_colors);
+            scanner.register(
+                Detection::StatusBar(kind),
+                Detector::new(bar.refs, bar.tolerance)
+                    .in_region(bar.min_x, bar.min_y, bar.max_x, bar.max_y),
+            );
+        }
+
+        scanner
+    }
+
+    /// Pixels found by a detector of `frame_scanner` in the current frame.
+    pub fn detected_pixels(&self, detection: Detection) -> Option<&PointCloud> {
+        self.scan.as_ref().and_then(|scan| scan.get(detection))
+    }
+
+    /// Find the pixels matching any of `colors` in a region of the current frame, a `max_x` or
+    /// `max_y` of 0 extends the region to the frame edge.
     pub fn pixel_detection(
         &self,
         colors: Vec<Color>,
         min_x: u32,
         min_y: u32,
-        mut max_x: u32,
-        mut max_y: u32,
+        max_x: u32,
+        max_y: u32,
         tolerence: Option<u8>,
-    ) -> Receiver<Point> {
-        let (snd, recv) = sync_channel::<Point>(4096);
+    ) -> PointCloud {
         let image = self.image.as_ref().unwrap();
-
-        if max_x == 0 {
-            max_x = image.width();
-        }
-
-        if max_y == 0 {
-            max_y = image.height();
-        }
-
-        image
-            .enumerate_rows()
-            .par_bridge()
-            .for_each(move |(y, row)| {
-                // Skip this row if it's in an ignored area
-                let image_height = image.height();
-                #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
-                if y <= IGNORE_AREA_TOP
-                    || y > image_height
-                        .checked_sub(IGNORE_AREA_BOTTOM)
-                        .unwrap_or(image_height)
-                    || y > IGNORE_AREA_TOP + max_y
-                    || y > max_y
-                    || y < min_y
-                {
-                    return;
-                }
-
-                // Loop over columns
-                'outer: for (x, _, px) in row {
-                    if px.0[3] != 255 || x >= max_x {
-                        return;
-                    } else if x < min_x {
-                        continue;
-                    }
-
-                    for ref_color in colors.iter() {
-                        // Check if the pixel matches any of the reference colors
-                        if Self::pixel_matches(&px.0, &ref_color.refs, tolerence.unwrap_or(5)) {
-                            #[allow(dropping_copy_types)]
-                            drop(snd.send(Point::new(x, y)));
-
-                            // Continue to next column
-                            continue 'outer;
-                        }
-                    }
-                }
-            });
-        recv
+        let mut scanner = FrameScanner::default();
+        scanner.register(
+            (),
+            Detector::new(colors, tolerence.unwrap_or(5)).in_region(min_x, min_y, max_x, max_y),
+        );
+        scanner
+            .scan(image, self.hud_layout().ignore_area_bottom)
+            .remove(())
+            .unwrap_or_default()
     }
 
     fn merge_cloud_into_mobs(
@@ -131,24 +236,22 @@ impl ImageAnalyzer {
     ) -> Vec<Target> {
         let _timer = Timer::start_new("merge_cloud_into_mobs");
 
-        // Max merge distance
-        let max_distance_x: u32 = 50;
-        let max_distance_y: u32 = 3;
-
-        // Cluster coordinates in x-direction
-        let x_clusters = cloud.cluster_by_distance(max_distance_x, point_selector::x_axis);
-
-        let mut xy_clusters = Vec::default();
-        for x_cluster in x_clusters {
-            // Cluster current x-cluster coordinates in y-direction
-            let local_y_clusters =
-                x_cluster.cluster_by_distance(max_distance_y, point_selector::y_axis);
-            // Extend final xy-clusters with local y-clusters
-            xy_clusters.extend(local_y_clusters.into_iter());
-        }
+        let clusters = if let Some(config) = config {
+            // Split into nameplates
+            let (dilate_x, dilate_y) = config.mobs_name_dilation();
+            cloud.connected_components(dilate_x, dilate_y)
+        } else {
+            // The target marker keeps the distance merge until recorded frames show it works
+            // with connected components too
+            cloud
+                .cluster_by_distance(50, point_selector::x_axis)
+                .into_iter()
+                .flat_map(|x_cluster| x_cluster.cluster_by_distance(3, point_selector::y_axis))
+                .collect()
+        };
 
         // Create mobs from clusters
-        xy_clusters
+        clusters
             .into_iter()
             .map(|cluster| Target {
                 target_type: mob_type,
@@ -182,72 +285,19 @@ impl ImageAnalyzer {
 
     pub fn identify_mobs(&self, config: &FarmingConfig) -> Vec<Target> {
         let _timer = Timer::start_new("identify_mobs");
-
-        // Create collections for passive and aggro mobs
-        let mut mob_coords_pas: Vec<Point> = Vec::default();
-        let mut mob_coords_agg: Vec<Point> = Vec::default();
-
-        // Reference colors
-        let ref_color_pas_wrapped: [Option<u8>; 3] = config.passive_mobs_colors(); // Passive mobs 234, 234, 149
-        let ref_color_agg_wrapped: [Option<u8>; 3] = config.aggressive_mobs_colors(); // Aggro mobs 179, 23, 23
-        let ref_color_pas: [u8; 3] = [
-            ref_color_pas_wrapped[0].unwrap_or(234),
-            ref_color_pas_wrapped[1].unwrap_or(234),
-            ref_color_pas_wrapped[2].unwrap_or(149),
-        ];
-        let ref_color_agg: [u8; 3] = [
-            ref_color_agg_wrapped[0].unwrap_or(179),
-            ref_color_agg_wrapped[1].unwrap_or(23),
-            ref_color_agg_wrapped[2].unwrap_or(23),
-        ];
-
-        // Collect pixel clouds
-        struct MobPixel(u32, u32, TargetType);
-        let (snd, recv) = sync_channel::<MobPixel>(4096);
-        let image = self.image.as_ref().unwrap();
-        image
-            .enumerate_rows()
-            .par_bridge()
-            .for_each(move |(y, row)| {
-                #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
-                if y <= IGNORE_AREA_TOP || y > image.height() - IGNORE_AREA_BOTTOM {
-                    return;
-                }
-                for (x, _, px) in row {
-                    if px.0[3] != 255 {
-                        return;
-                    } else if x <= 250 && y <= 110 {
-                        // avoid detect the health bar as a monster
-                        continue;
-                    }
-                    if Self::pixel_matches(&px.0, &ref_color_pas, config.passive_tolerence()) {
-                        drop(snd.send(MobPixel(x, y, TargetType::Mob(MobType::Passive))));
-                    } else if Self::pixel_matches(
-                        &px.0,
-                        &ref_color_agg,
-                        config.aggressive_tolerence(),
-                    ) {
-                        drop(snd.send(MobPixel(x, y, TargetType::Mob(MobType::Aggressive))));
-                    }
-                }
-            });
-        while let Ok(px) = recv.recv() {
-            match px.2 {
-                TargetType::Mob(MobType::Passive) => mob_coords_pas.push(Point::new(px.0, px.1)),
-                TargetType::Mob(MobType::Aggressive) => mob_coords_agg.push(Point::new(px.0, px.1)),
-                _ => unreachable!(),
-            }
-        }
+        let empty = PointCloud::default();
 
         // Categorize mobs
         let mobs_pas = Self::merge_cloud_into_mobs(
             Some(config),
-            &PointCloud::new(mob_coords_pas),
+            self.detected_pixels(Detection::PassiveMobs)
+                .unwrap_or(&empty),
             TargetType::Mob(MobType::Passive),
         );
         let mobs_agg = Self::merge_cloud_into_mobs(
             Some(config),
-            &PointCloud::new(mob_coords_agg),
+            self.detected_pixels(Detection::AggressiveMobs)
+                .unwrap_or(&empty),
             TargetType::Mob(MobType::Aggressive),
         );
 
@@ -257,28 +307,17 @@ impl ImageAnalyzer {
 
     pub fn identify_target_marker(&self, blank_target: bool) -> Option<Target> {
         let _timer = Timer::start_new("identify_target_marker");
-        let mut coords = Vec::default();
-
-        // Reference color
-        let ref_color: Color = {
-            if !blank_target {
-                Color::new(246, 90, 106)
-            } else {
-                Color::new(164, 180, 226)
-            }
+        let detection = if !blank_target {
+            Detection::TargetMarker
+        } else {
+            Detection::BlankTargetMarker
         };
 
-        // Collect pixel clouds
-        let recv = self.pixel_detection(vec![ref_color], 0, 0, 0, 0, None);
-
-        // Receive points from channel
-        while let Ok(point) = recv.recv() {
-            coords.push(point);
-        }
-
         // Identify target marker entities
-        let target_markers =
-            Self::merge_cloud_into_mobs(None, &PointCloud::new(coords), TargetType::TargetMarker);
+        let target_markers = match self.detected_pixels(detection) {
+            Some(cloud) => Self::merge_cloud_into_mobs(None, cloud, TargetType::TargetMarker),
+            None => vec![],
+        };
 
         if !blank_target && target_markers.is_empty() {
             return self.identify_target_marker(true);
@@ -366,3 +405,177 @@ impl ImageAnalyzer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use serde::Deserialize;
+    use slog::Logger;
+
+    use super::{FrameSource, ImageAnalyzer};
+    use crate::{
+        data::{Bounds, MobType, Target, TargetType},
+        ipc::FarmingConfig,
+    };
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum ExpectedMobKind {
+        Passive,
+        Aggressive,
+    }
+
+    #[derive(Deserialize)]
+    struct ExpectedMob {
+        kind: ExpectedMobKind,
+        bounds: Bounds,
+    }
+
+    #[derive(Deserialize)]
+    struct ExpectedFrame {
+        frame: String,
+        hp: u32,
+        mp: u32,
+        fp: u32,
+        target_hp: u32,
+        target_mp: u32,
+        mobs: Vec<ExpectedMob>,
+        target_marker: Option<Bounds>,
+    }
+
+    fn fixtures_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/frames")
+    }
+
+    fn bounds_key(bounds: &Bounds) -> (u32, u32, u32, u32) {
+        (bounds.x, bounds.y, bounds.w, bounds.h)
+    }
+
+    fn mob_key(mob: &Target) -> (bool, (u32, u32, u32, u32)) {
+        (
+            mob.target_type == TargetType::Mob(MobType::Aggressive),
+            bounds_key(&mob.bounds),
+        )
+    }
+
+    #[test]
+    fn test_frame_sequences() {
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let config = FarmingConfig::default();
+
+        let mut sequences = fs::read_dir(fixtures_dir())
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        sequences.sort();
+        assert!(!sequences.is_empty());
+
+        for sequence in sequences {
+            let expected_frames: Vec<ExpectedFrame> =
+                serde_json::from_reader(fs::File::open(sequence.join("expected.json")).unwrap())
+                    .unwrap();
+            let mut analyzer =
+                ImageAnalyzer::from_frame_source(FrameSource::from_path(&sequence).unwrap(), None);
+
+            for expected in expected_frames {
+                analyzer.capture_window(&logger, &config);
+                let frame = analyzer.frame_source().to_string();
+                assert!(frame.ends_with(&expected.frame), "{} is out of sync", frame);
+
+                analyzer.client_stats.update(&analyzer.clone(), &logger);
+                let stats = &analyzer.client_stats;
+                assert_eq!(stats.hp.value, expected.hp, "HP of {}", frame);
+                assert_eq!(stats.mp.value, expected.mp, "MP of {}", frame);
+                assert_eq!(stats.fp.value, expected.fp, "FP of {}", frame);
+                assert_eq!(
+                    stats.target_hp.value, expected.target_hp,
+                    "target HP of {}",
+                    frame
+                );
+                assert_eq!(
+                    stats.target_mp.value, expected.target_mp,
+                    "target MP of {}",
+                    frame
+                );
+
+                let mut mobs = analyzer
+                    .identify_mobs(&config)
+                    .iter()
+                    .map(mob_key)
+                    .collect::<Vec<_>>();
+                let mut expected_mobs = expected
+                    .mobs
+                    .iter()
+                    .map(|mob| {
+                        (
+                            matches!(mob.kind, ExpectedMobKind::Aggressive),
+                            bounds_key(&mob.bounds),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                mobs.sort();
+                expected_mobs.sort();
+                assert_eq!(mobs, expected_mobs, "mobs of {}", frame);
+
+                let marker = analyzer
+                    .identify_target_marker(false)
+                    .map(|marker| bounds_key(&marker.bounds));
+                assert_eq!(
+                    marker,
+                    expected.target_marker.as_ref().map(bounds_key),
+                    "target marker of {}",
+                    frame
+                );
+            }
+            assert!(analyzer.frame_source().is_exhausted());
+        }
+    }
+
+    #[test]
+    fn test_single_frame_is_replayed() {
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let config = FarmingConfig::default();
+        let path = fixtures_dir().join("basic/frame_001.png");
+        let mut analyzer =
+            ImageAnalyzer::from_frame_source(FrameSource::from_path(path).unwrap(), None);
+
+        for _ in 0..3 {
+            analyzer.capture_window(&logger, &config);
+            analyzer.client_stats.update(&analyzer.clone(), &logger);
+            assert!(analyzer.image_is_some());
+            assert!(!analyzer.frame_source().is_exhausted());
+            assert_eq!(analyzer.identify_mobs(&config).len(), 2);
+        }
+        // The bar is the widest one seen so far, so it reads as full
+        assert_eq!(analyzer.client_stats.hp.value, 100);
+    }
+
+    #[test]
+    fn test_downscaled_mobs_scan() {
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let full_resolution = FarmingConfig::default();
+        let mut downscaled = serde_json::to_value(&full_resolution).unwrap();
+        downscaled["mobs_scan_downscale"] = serde_json::json!(2);
+        let downscaled: FarmingConfig = serde_json::from_value(downscaled).unwrap();
+
+        let mobs = |config: &FarmingConfig| {
+            let path = fixtures_dir().join("basic/frame_000.png");
+            let mut analyzer =
+                ImageAnalyzer::from_frame_source(FrameSource::from_path(path).unwrap(), None);
+            analyzer.capture_window(&logger, config);
+            let mut mobs = analyzer
+                .identify_mobs(config)
+                .iter()
+                .map(mob_key)
+                .collect::<Vec<_>>();
+            mobs.sort();
+            mobs
+        };
+        // Refinement recovers the exact nameplate bounds
+        assert!(!mobs(&full_resolution).is_empty());
+        assert_eq!(mobs(&downscaled), mobs(&full_resolution));
+    }
+}
@@ -0,0 +1,350 @@
+use std::time::{Duration, Instant};
+
+use libscreenshot::ImageBuffer;
+
+use super::ImageAnalyzer;
+use crate::ipc::{HudRegion, SlotBar};
+
+/// Slots of the visible slot bar, by slot index from the left
+pub const ACTION_BAR_SLOTS: usize = 10;
+
+/// Time the client takes to draw the cooldown overlay once a slot was pressed
+const COOLDOWN_OVERLAY_DELAY: Duration = Duration::from_millis(300);
+
+/// A skill still looking on cooldown this long after its configured cooldown is released anyway,
+/// e.g. its ready icon was never seen and the brightness reference is off
+const COOLDOWN_FALLBACK_MARGIN: Duration = Duration::from_secs(2);
+
+/// Mean chroma (max - min channel) under which a slot counts as empty. Item icons are colorful
+/// while the slot background and greyed-out icons are not.
+const MIN_ICON_CHROMA: u32 = 16;
+
+/// A skill is cooling down while its icon is darker than 3/4 of its ready brightness, the
+/// cooldown sweep covers the icon with a dark overlay
+const READY_BRIGHTNESS_RATIO: (u32, u32) = (3, 4);
+
+/// A sixth of the slot is skipped on every side, the slot frame is grey
+const SLOT_MARGIN_DIVISOR: u32 = 6;
+
+/// Mean colors of a slot icon on the visible slot bar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlotAppearance {
+    /// Mean of max - min channel
+    pub chroma: u32,
+    /// Mean of the channels
+    pub brightness: u32,
+}
+
+impl SlotAppearance {
+    /// The slot is unused or its icon is greyed out, e.g. an empty stack
+    pub fn is_empty(&self) -> bool {
+        self.chroma < MIN_ICON_CHROMA
+    }
+}
+
+impl ImageAnalyzer {
+    /// Appearance of every slot of the visible slot bar, by slot index.
+    pub fn action_bar_slots(&self) -> Option<[SlotAppearance; ACTION_BAR_SLOTS]> {
+        slot_appearances(self.image.as_ref()?, self.hud_layout().action_bar)
+    }
+}
+
+fn slot_appearances(
+    image: &ImageBuffer,
+    region: HudRegion,
+) -> Option<[SlotAppearance; ACTION_BAR_SLOTS]> {
+    let max_x = region.max_x.min(image.width().saturating_sub(1));
+    let max_y = region.max_y.min(image.height().saturating_sub(1));
+    if region.min_x > max_x || region.min_y > max_y {
+        return None;
+    }
+
+    let mut appearances = [SlotAppearance::default(); ACTION_BAR_SLOTS];
+    let slot_width = (max_x - region.min_x + 1) / ACTION_BAR_SLOTS as u32;
+    let margin_x = slot_width / SLOT_MARGIN_DIVISOR;
+    let margin_y = (max_y - region.min_y + 1) / SLOT_MARGIN_DIVISOR;
+    for (slot_index, appearance) in appearances.iter_mut().enumerate() {
+        let slot_min_x = region.min_x + slot_index as u32 * slot_width + margin_x;
+        let slot_max_x = slot_min_x + slot_width - 2 * margin_x;
+
+        let mut chroma_sum = 0;
+        let mut brightness_sum = 0;
+        let mut pixel_count: u64 = 0;
+        for y in region.min_y + margin_y..=max_y - margin_y {
+            for x in slot_min_x..slot_max_x {
+                let [r, g, b, _] = image.get_pixel(x, y).0;
+                chroma_sum += (r.max(g).max(b) - r.min(g).min(b)) as u64;
+                brightness_sum += (r as u64 + g as u64 + b as u64) / 3;
+                pixel_count += 1;
+            }
+        }
+        // Slots too small to tell are never reported empty
+        appearance.chroma = chroma_sum
+            .checked_div(pixel_count)
+            .map_or(u32::MAX, |chroma| chroma as u32);
+        appearance.brightness = brightness_sum.checked_div(pixel_count).unwrap_or(0) as u32;
+    }
+    Some(appearances)
+}
+
+/// What the bot saw of its slot bars. Only the slot bar selected last is visible.
+#[derive(Debug, Clone)]
+pub struct ActionBarState {
+    visible_slot_bar: usize,
+    /// Appearance of every slot the last time its slot bar was visible
+    appearances: [[Option<SlotAppearance>; ACTION_BAR_SLOTS]; 9],
+    /// Brightest appearance of every slot, i.e. its icon when the skill is ready
+    ready_brightness: [[u32; ACTION_BAR_SLOTS]; 9],
+}
+
+impl Default for ActionBarState {
+    fn default() -> Self {
+        Self {
+            visible_slot_bar: 0,
+            appearances: [[None; ACTION_BAR_SLOTS]; 9],
+            ready_brightness: [[0; ACTION_BAR_SLOTS]; 9],
+        }
+    }
+}
+
+impl ActionBarState {
+    /// A slot of `slot_bar_index` was pressed, selecting its slot bar
+    pub fn set_visible_slot_bar(&mut self, slot_bar_index: usize) {
+        self.visible_slot_bar = slot_bar_index;
+    }
+
+    /// Store what the current frame shows of the visible slot bar.
+    pub fn update(&mut self, appearances: [SlotAppearance; ACTION_BAR_SLOTS]) {
+        let slot_bar_index = self.visible_slot_bar;
+        for (slot_index, appearance) in appearances.into_iter().enumerate() {
+            self.appearances[slot_bar_index][slot_index] = Some(appearance);
+            if !appearance.is_empty() {
+                let ready_brightness = &mut self.ready_brightness[slot_bar_index][slot_index];
+                *ready_brightness = (*ready_brightness).max(appearance.brightness);
+            }
+        }
+    }
+
+    /// Forget everything, e.g. once slots were reassigned
+    pub fn reset(&mut self) {
+        *self = Self {
+            visible_slot_bar: self.visible_slot_bar,
+            ..Self::default()
+        };
+    }
+
+    /// Slots that looked empty the last time their slot bar was visible.
+    pub fn empty_slots(&self) -> [[bool; ACTION_BAR_SLOTS]; 9] {
+        let mut empty_slots = [[false; ACTION_BAR_SLOTS]; 9];
+        for (slot_bar_index, appearances) in self.appearances.iter().enumerate() {
+            for (slot_index, appearance) in appearances.iter().enumerate() {
+                empty_slots[slot_bar_index][slot_index] =
+                    appearance.map_or(false, |appearance| appearance.is_empty());
+            }
+        }
+        empty_slots
+    }
+
+    /// Whether a skill slot looks ready. Unknown unless its slot bar is visible, since cooldowns
+    /// of hidden slot bars keep running.
+    pub fn is_ready(&self, slot_bar_index: usize, slot_index: usize) -> Option<bool> {
+        if slot_bar_index != self.visible_slot_bar {
+            return None;
+        }
+        let appearance = self.appearances[slot_bar_index][slot_index]?;
+        let ready_brightness = self.ready_brightness[slot_bar_index][slot_index];
+        if appearance.is_empty() || ready_brightness == 0 {
+            return None;
+        }
+        let (numerator, denominator) = READY_BRIGHTNESS_RATIO;
+        Some(appearance.brightness * denominator >= ready_brightness * numerator)
+    }
+
+    /// Forget when the slots that are ready again were pressed. Skills are ready once the slot
+    /// bar says so, the configured cooldown is used when it can't tell or with `cooldown_detection`
+    /// off.
+    pub fn release_ready_slots(
+        &self,
+        slot_bars: &[SlotBar],
+        last_usage: &mut [[Option<Instant>; ACTION_BAR_SLOTS]; 9],
+        cooldown_detection: bool,
+        now: Instant,
+    ) {
+        for (slot_bar_index, slot_bar) in slot_bars.iter().enumerate().take(last_usage.len()) {
+            for (slot_index, slot) in slot_bar.slots().into_iter().enumerate() {
+                let last_time = match last_usage[slot_bar_index].get(slot_index) {
+                    Some(Some(last_time)) => *last_time,
+                    _ => continue,
+                };
+                let elapsed = now.saturating_duration_since(last_time);
+                let cooldown = slot.get_slot_cooldown().unwrap_or(100) as u64;
+                let is_cooldown_over = elapsed.as_millis() > cooldown as u128;
+
+                // The overlay is not drawn yet right after the slot was pressed
+                let visual_state = if cooldown_detection
+                    && slot.slot_type().is_skill()
+                    && elapsed >= COOLDOWN_OVERLAY_DELAY
+                {
+                    self.is_ready(slot_bar_index, slot_index)
+                } else {
+                    None
+                };
+                let is_ready = match visual_state {
+                    Some(true) => true,
+                    Some(false) => {
+                        elapsed > Duration::from_millis(cooldown) + COOLDOWN_FALLBACK_MARGIN
+                    }
+                    None => is_cooldown_over,
+                };
+                if is_ready {
+                    last_usage[slot_bar_index][slot_index] = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use image::Rgba;
+    use libscreenshot::ImageBuffer;
+    use serde_json::json;
+
+    use super::{slot_appearances, ActionBarState, SlotAppearance};
+    use crate::ipc::{HudLayout, SlotBar};
+
+    #[test]
+    fn test_slot_appearances() {
+        let layout = HudLayout::default().scaled_to(1600, 1200);
+        let region = layout.action_bar;
+        let slot_width = (region.max_x - region.min_x + 1) / 10;
+        let mut image = ImageBuffer::from_pixel(1600, 1200, Rgba([30, 30, 30, 255]));
+
+        // A food icon, a greyed-out pill and a skill, the other slots are unused
+        let icons = [
+            (1, [200, 120, 40]),
+            (2, [110, 110, 118]),
+            (5, [40, 90, 220]),
+        ];
+        for (slot_index, color) in icons {
+            let min_x = region.min_x + slot_index * slot_width;
+            for x in min_x + 4..min_x + slot_width - 4 {
+                for y in region.min_y + 4..region.max_y - 4 {
+                    image.put_pixel(x, y, Rgba([color[0], color[1], color[2], 255]));
+                }
+            }
+        }
+
+        let appearances = slot_appearances(&image, region).unwrap();
+        let mut expected = [true; 10];
+        expected[1] = false;
+        expected[5] = false;
+        assert_eq!(
+            appearances.map(|appearance| appearance.is_empty()),
+            expected
+        );
+        assert_eq!(appearances[5].brightness, 116);
+
+        // Nothing to tell outside of the frame
+        let outside = HudLayout::default().scaled_to(3200, 2400).action_bar;
+        assert_eq!(slot_appearances(&image, outside), None);
+    }
+
+    #[test]
+    fn test_cooldown_state() {
+        let skill = |brightness| SlotAppearance {
+            chroma: 80,
+            brightness,
+        };
+        let empty = SlotAppearance {
+            chroma: 0,
+            brightness: 30,
+        };
+        let mut state = ActionBarState::default();
+        assert_eq!(state.is_ready(0, 0), None);
+
+        // Ready icon, then covered by the cooldown overlay
+        state.update([
+            skill(120),
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+        ]);
+        assert_eq!(state.is_ready(0, 0), Some(true));
+        assert_eq!(state.is_ready(0, 1), None);
+        state.update([
+            skill(60),
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+            empty,
+        ]);
+        assert_eq!(state.is_ready(0, 0), Some(false));
+        assert!(state.empty_slots()[0][1]);
+
+        // Another slot bar was selected, the cooldown keeps running out of sight
+        state.set_visible_slot_bar(1);
+        assert_eq!(state.is_ready(0, 0), None);
+        assert!(state.empty_slots()[0][1]);
+
+        state.reset();
+        assert!(!state.empty_slots()[0][1]);
+    }
+
+    #[test]
+    fn test_release_ready_slots() {
+        let skill = |brightness| SlotAppearance {
+            chroma: 80,
+            brightness,
+        };
+        let slot = |slot_type: &str| json!({ "slot_type": slot_type, "slot_cooldown": 1000, "slot_enabled": true });
+        let mut slots = vec![slot("Unused"); 10];
+        slots[0] = slot("AttackSkill");
+        slots[1] = slot("Food");
+        let slot_bars = vec![serde_json::from_value::<SlotBar>(json!({ "slots": slots })).unwrap()];
+
+        // The skill looks ready before its configured cooldown, the food follows its cooldown
+        let mut state = ActionBarState::default();
+        state.update([skill(60); 10]);
+        let mut ready = [skill(60); 10];
+        ready[0] = skill(120);
+        state.update(ready);
+        let start = Instant::now();
+        let mut last_usage = [[None; 10]; 9];
+        last_usage[0][0] = Some(start);
+        last_usage[0][1] = Some(start);
+        let now = start + Duration::from_millis(500);
+        state.release_ready_slots(&slot_bars, &mut last_usage, true, now);
+        assert_eq!(last_usage[0][0], None);
+        assert_eq!(last_usage[0][1], Some(start));
+
+        // Looking on cooldown holds the skill past its configured cooldown, but not forever
+        state.update([skill(60); 10]);
+        last_usage[0][0] = Some(start);
+        let later = start + Duration::from_millis(2500);
+        state.release_ready_slots(&slot_bars, &mut last_usage, true, later);
+        assert_eq!(last_usage[0][0], Some(start));
+        assert_eq!(last_usage[0][1], None);
+        let much_later = start + Duration::from_millis(3500);
+        state.release_ready_slots(&slot_bars, &mut last_usage, true, much_later);
+        assert_eq!(last_usage[0][0], None);
+
+        // Without detection the configured cooldown is used
+        last_usage[0][0] = Some(start);
+        state.release_ready_slots(&slot_bars, &mut last_usage, false, later);
+        assert_eq!(last_usage[0][0], None);
+    }
+}
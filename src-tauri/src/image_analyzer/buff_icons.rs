@@ -0,0 +1,169 @@
+use libscreenshot::ImageBuffer;
+use serde::{Deserialize, Serialize};
+
+use super::ImageAnalyzer;
+use crate::{data::Point, ipc::HudRegion};
+
+/// Side of the square cut around the clicked pixel. Smaller than a buff icon so a click slightly
+/// off center still only captures the icon, not the background around it.
+const ICON_TEMPLATE_SIZE: u32 = 12;
+
+/// Mean channel difference under which the buff area matches a template, icons are drawn over a
+/// translucent background and may be scaled slightly
+const MAX_MEAN_DIFFERENCE: u32 = 24;
+
+/// Pixels of a buff icon captured from the client, row by row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IconTemplate {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl ImageAnalyzer {
+    /// Cut the icon under `point`, e.g. a buff icon clicked in a capture of the client.
+    pub fn icon_template(&self, point: Point) -> Option<IconTemplate> {
+        crop_template(self.image.as_ref()?, point)
+    }
+
+    /// Whether `template` is shown in the buff area, `None` if there is no frame.
+    pub fn is_icon_visible(&self, template: &IconTemplate) -> Option<bool> {
+        Some(find_template(
+            self.image.as_ref()?,
+            self.hud_layout().buff_area,
+            template,
+        ))
+    }
+}
+
+fn crop_template(image: &ImageBuffer, point: Point) -> Option<IconTemplate> {
+    let min_x = point.x.checked_sub(ICON_TEMPLATE_SIZE / 2)?;
+    let min_y = point.y.checked_sub(ICON_TEMPLATE_SIZE / 2)?;
+    if min_x + ICON_TEMPLATE_SIZE > image.width() || min_y + ICON_TEMPLATE_SIZE > image.height() {
+        return None;
+    }
+
+    let mut pixels = Vec::with_capacity((ICON_TEMPLATE_SIZE * ICON_TEMPLATE_SIZE) as usize);
+    for y in min_y..min_y + ICON_TEMPLATE_SIZE {
+        for x in min_x..min_x + ICON_TEMPLATE_SIZE {
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            pixels.push([r, g, b]);
+        }
+    }
+    Some(IconTemplate {
+        width: ICON_TEMPLATE_SIZE,
+        height: ICON_TEMPLATE_SIZE,
+        pixels,
+    })
+}
+
+/// Slide `template` over `region` until a position matches.
+fn find_template(image: &ImageBuffer, region: HudRegion, template: &IconTemplate) -> bool {
+    if template.width == 0
+        || template.height == 0
+        || template.pixels.len() as u64 != template.width as u64 * template.height as u64
+    {
+        return false;
+    }
+    let max_x = region.max_x.min(image.width().saturating_sub(1));
+    let max_y = region.max_y.min(image.height().saturating_sub(1));
+    if region.min_x + template.width > max_x + 1 || region.min_y + template.height > max_y + 1 {
+        return false;
+    }
+
+    // Summed over the 3 channels of every pixel
+    let max_difference = MAX_MEAN_DIFFERENCE as u64 * template.pixels.len() as u64 * 3;
+    for y in region.min_y..=max_y + 1 - template.height {
+        for x in region.min_x..=max_x + 1 - template.width {
+            if difference_at(image, x, y, template, max_difference) <= max_difference {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Sum of the channel differences of the template placed at `x`, `y`. Stops counting once it
+/// exceeds `max_difference`, most positions are obviously different.
+fn difference_at(
+    image: &ImageBuffer,
+    x: u32,
+    y: u32,
+    template: &IconTemplate,
+    max_difference: u64,
+) -> u64 {
+    let mut difference = 0;
+    for (index, expected) in template.pixels.iter().enumerate() {
+        let index = index as u32;
+        let pixel = image.get_pixel(x + index % template.width, y + index / template.width);
+        for channel in 0..3 {
+            difference += (pixel[channel] as i32 - expected[channel] as i32).unsigned_abs() as u64;
+        }
+        if difference > max_difference {
+            break;
+        }
+    }
+    difference
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+    use libscreenshot::ImageBuffer;
+
+    use super::{crop_template, find_template};
+    use crate::{data::Point, ipc::HudLayout};
+
+    /// Checkered 20x20 icon, the client draws buff icons at about this size
+    fn draw_icon(image: &mut ImageBuffer, min_x: u32, min_y: u32, colors: [[u8; 3]; 2]) {
+        for y in 0..20 {
+            for x in 0..20 {
+                let [r, g, b] = colors[((x / 4 + y / 4) % 2) as usize];
+                image.put_pixel(min_x + x, min_y + y, Rgba([r, g, b, 255]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_buff_icons() {
+        let region = HudLayout::default().buff_area;
+        let mut capture = ImageBuffer::from_pixel(800, 600, Rgba([20, 24, 30, 255]));
+        draw_icon(
+            &mut capture,
+            region.min_x + 4,
+            region.min_y + 4,
+            [[220, 180, 40], [90, 40, 10]],
+        );
+        draw_icon(
+            &mut capture,
+            region.min_x + 30,
+            region.min_y + 4,
+            [[40, 200, 220], [10, 60, 90]],
+        );
+
+        // Clicked a bit off the center of the first icon
+        let template =
+            crop_template(&capture, Point::new(region.min_x + 12, region.min_y + 15)).unwrap();
+        assert_eq!(template.pixels.len(), 144);
+        assert_eq!(crop_template(&capture, Point::new(2, 2)), None);
+
+        // Later on the icon moved to another position, the other buff ran out
+        let mut frame = ImageBuffer::from_pixel(800, 600, Rgba([20, 24, 30, 255]));
+        draw_icon(
+            &mut frame,
+            region.min_x + 56,
+            region.min_y + 10,
+            [[220, 180, 40], [90, 40, 10]],
+        );
+        assert!(find_template(&frame, region, &template));
+
+        let other =
+            crop_template(&capture, Point::new(region.min_x + 40, region.min_y + 14)).unwrap();
+        assert!(!find_template(&frame, region, &other));
+
+        // Icons outside of the buff area are ignored
+        let mut outside = ImageBuffer::from_pixel(800, 600, Rgba([20, 24, 30, 255]));
+        draw_icon(&mut outside, 400, 300, [[220, 180, 40], [90, 40, 10]]);
+        assert!(!find_template(&outside, region, &template));
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::{HashMap, VecDeque};
+
+use image::{codecs::png::PngEncoder, ColorType, ImageEncoder};
+use serde::{Deserialize, Serialize};
+
+use super::ImageAnalyzer;
+use crate::data::{Bounds, Point};
+
+/// Size of the area around the clicked pixel searched for similar colors
+const SEARCH_AREA_SIZE: (u32, u32) = (200, 24);
+
+/// Fewer matching pixels are most likely a misclick
+const MIN_PIXEL_COUNT: usize = 4;
+
+/// HUD element the user clicked on during calibration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CalibrationTarget {
+    PassiveMob,
+    AggressiveMob,
+    HpBar,
+    MpBar,
+    FpBar,
+}
+
+impl CalibrationTarget {
+    /// Max channel difference to the clicked pixel. Bars are gradients so they need more room.
+    fn max_spread(self) -> u8 {
+        match self {
+            CalibrationTarget::PassiveMob | CalibrationTarget::AggressiveMob => 24,
+            CalibrationTarget::HpBar | CalibrationTarget::MpBar | CalibrationTarget::FpBar => 48,
+        }
+    }
+
+    /// Number of reference colors to derive.
+    fn color_count(self) -> usize {
+        match self {
+            CalibrationTarget::PassiveMob | CalibrationTarget::AggressiveMob => 1,
+            CalibrationTarget::HpBar | CalibrationTarget::MpBar | CalibrationTarget::FpBar => 4,
+        }
+    }
+
+    /// Tolerance used when the clicked element has a flat color.
+    fn min_tolerance(self) -> u8 {
+        match self {
+            CalibrationTarget::AggressiveMob => 10,
+            _ => 5,
+        }
+    }
+}
+
+/// Reference colors derived from a clicked HUD element.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColorCalibration {
+    pub target: CalibrationTarget,
+    /// Most frequent colors first
+    pub colors: Vec<[u8; 3]>,
+    /// Smallest tolerance matching every pixel of the element
+    pub tolerance: u8,
+    pub pixel_count: usize,
+}
+
+impl ImageAnalyzer {
+    /// Derive reference colors from the element under `point`, e.g. a letter of a nameplate.
+    pub fn calibrate(&self, target: CalibrationTarget, point: Point) -> Option<ColorCalibration> {
+        let image = self.image.as_ref()?;
+        if point.x >= image.width() || point.y >= image.height() {
+            return None;
+        }
+
+        let area = Bounds::new(
+            point.x.saturating_sub(SEARCH_AREA_SIZE.0 / 2),
+            point.y.saturating_sub(SEARCH_AREA_SIZE.1 / 2),
+            SEARCH_AREA_SIZE.0,
+            SEARCH_AREA_SIZE.1,
+        );
+        let seed = rgb(image.get_pixel(point.x, point.y).0);
+        let max_spread = target.max_spread();
+
+        // Flood fill pixels connected to the clicked one with a similar color
+        let mut visited = vec![false; ((area.w + 1) * (area.h + 1)) as usize];
+        let visited_index = |p: Point| ((p.y - area.y) * (area.w + 1) + (p.x - area.x)) as usize;
+        visited[visited_index(point)] = true;
+        let mut pixel_count = 0;
+        let mut queue = VecDeque::from([point]);
+        let mut histogram: HashMap<[u8; 3], usize> = HashMap::new();
+        while let Some(current) = queue.pop_front() {
+            pixel_count += 1;
+            *histogram
+                .entry(rgb(image.get_pixel(current.x, current.y).0))
+                .or_default() += 1;
+
+            let neighbors = [
+                (current.x.checked_sub(1), Some(current.y)),
+                (current.x.checked_add(1), Some(current.y)),
+                (Some(current.x), current.y.checked_sub(1)),
+                (Some(current.x), current.y.checked_add(1)),
+            ];
+            for neighbor in neighbors {
+                let neighbor = match neighbor {
+                    (Some(x), Some(y)) => Point::new(x, y),
+                    _ => continue,
+                };
+                if neighbor.x >= image.width()
+                    || neighbor.y >= image.height()
+                    || !area.contains_point(&neighbor)
+                    || visited[visited_index(neighbor)]
+                {
+                    continue;
+                }
+                let px = image.get_pixel(neighbor.x, neighbor.y).0;
+                if px[3] == 255 && channel_distance(&rgb(px), &seed) <= max_spread {
+                    visited[visited_index(neighbor)] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        if pixel_count < MIN_PIXEL_COUNT {
+            return None;
+        }
+
+        let mut by_frequency = histogram.iter().collect::<Vec<_>>();
+        by_frequency.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let colors = by_frequency
+            .iter()
+            .take(target.color_count())
+            .map(|(color, _)| **color)
+            .collect::<Vec<_>>();
+
+        let tolerance = histogram
+            .keys()
+            .map(|color| {
+                colors
+                    .iter()
+                    .map(|reference| channel_distance(color, reference))
+                    .min()
+                    .unwrap_or(u8::MAX)
+            })
+            .max()
+            .unwrap_or_default()
+            .max(target.min_tolerance());
+
+        Some(ColorCalibration {
+            target,
+            colors,
+            tolerance,
+            pixel_count,
+        })
+    }
+
+    /// Encode the current frame as PNG, e.g. to show it to the user.
+    pub fn encode_png(&self) -> Option<Vec<u8>> {
+        let image = self.image.as_ref()?;
+        let mut buffer = Vec::new();
+        PngEncoder::new(&mut buffer)
+            .write_image(image, image.width(), image.height(), ColorType::Rgba8)
+            .ok()?;
+        Some(buffer)
+    }
+}
+
+fn rgb(px: [u8; 4]) -> [u8; 3] {
+    [px[0], px[1], px[2]]
+}
+
+fn channel_distance(a: &[u8; 3], b: &[u8; 3]) -> u8 {
+    (0..3)
+        .map(|i| a[i].max(b[i]) - a[i].min(b[i]))
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use slog::Logger;
+
+    use super::CalibrationTarget;
+    use crate::{
+        data::Point,
+        image_analyzer::{FrameSource, ImageAnalyzer},
+        ipc::FarmingConfig,
+    };
+
+    #[test]
+    fn test_calibrate() {
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let frame =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/frames/basic/frame_000.png");
+        let mut analyzer =
+            ImageAnalyzer::from_frame_source(FrameSource::from_path(frame).unwrap(), None);
+        let mut config = FarmingConfig::default();
+        analyzer.capture_window(&logger, &config);
+
+        assert!(analyzer
+            .calibrate(CalibrationTarget::PassiveMob, Point::new(800, 10))
+            .is_none());
+
+        let passive = analyzer
+            .calibrate(CalibrationTarget::PassiveMob, Point::new(410, 253))
+            .unwrap();
+        assert_eq!(passive.colors, vec![[234, 234, 149]]);
+        assert_eq!(passive.tolerance, 5);
+
+        let mut hp = analyzer
+            .calibrate(CalibrationTarget::HpBar, Point::new(120, 41))
+            .unwrap();
+        hp.colors.sort();
+        assert_eq!(
+            hp.colors,
+            vec![[174, 18, 55], [188, 24, 62], [204, 30, 70], [220, 36, 78]]
+        );
+
+        config.apply_calibration(&passive);
+        config.apply_calibration(&hp);
+        assert_eq!(
+            config.passive_mobs_colors(),
+            [Some(234), Some(234), Some(149)]
+        );
+        assert_eq!(config.status_bar_colors().hp, Some(hp.colors));
+        assert_eq!(config.status_bar_colors().hp_tolerance, Some(hp.tolerance));
+        assert_eq!(config.status_bar_colors().mp, None);
+        assert_eq!(config.status_bar_colors().mp_tolerance, None);
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use libscreenshot::{ImageBuffer, WindowCaptureProvider};
+
+/// Reason why no frame could be produced.
+#[derive(Debug)]
+pub enum FrameError {
+    /// No window handle or capture provider is available.
+    Unavailable,
+    /// The window could not be captured.
+    CaptureFailed,
+    /// Every frame of a disk sequence has already been consumed.
+    Exhausted,
+    /// A frame file could not be read or decoded.
+    Decode(PathBuf, image::ImageError),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::Unavailable => write!(f, "no capture source available"),
+            FrameError::CaptureFailed => write!(f, "window capture failed"),
+            FrameError::Exhausted => write!(f, "no frames left"),
+            FrameError::Decode(path, err) => write!(f, "{}: {}", path.display(), err),
+        }
+    }
+}
+
+/// Screenshots loaded from disk, either a single file or a directory sequence.
+#[derive(Debug, Clone)]
+pub struct DiskFrames {
+    paths: Vec<PathBuf>,
+    cursor: usize,
+    repeat: bool,
+}
+
+impl DiskFrames {
+    /// Load a single PNG file (replayed forever) or every PNG of a directory in file name order.
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if path.is_file() {
+            return Ok(Self {
+                paths: vec![path.to_path_buf()],
+                cursor: 0,
+                repeat: true,
+            });
+        }
+
+        let mut paths = fs::read_dir(path)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |ext| ext.eq_ignore_ascii_case("png"))
+            })
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        if paths.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no png frames in {}", path.display()),
+            ));
+        }
+
+        Ok(Self {
+            paths,
+            cursor: 0,
+            repeat: false,
+        })
+    }
+
+    /// Number of frames in the sequence.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Path of the frame returned by the last `next_frame` call.
+    pub fn current_path(&self) -> Option<&Path> {
+        self.cursor
+            .checked_sub(1)
+            .and_then(|index| self.paths.get(index))
+            .map(|path| path.as_path())
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        !self.repeat && self.cursor >= self.paths.len()
+    }
+
+    fn next_frame(&mut self) -> Result<ImageBuffer, FrameError> {
+        if self.is_exhausted() {
+            return Err(FrameError::Exhausted);
+        }
+        // A single file is replayed, so the cursor never moves past it
+        let index = self.cursor.min(self.paths.len() - 1);
+        self.cursor = index + 1;
+
+        let path = &self.paths[index];
+        image::open(path)
+            .map(|image| image.to_rgba8())
+            .map_err(|err| FrameError::Decode(path.clone(), err))
+    }
+}
+
+/// Where `ImageAnalyzer` pulls its frames from.
+#[derive(Debug, Clone)]
+pub enum FrameSource {
+    /// Live capture of a native window through `libscreenshot`.
+    Window(u64),
+    /// Screenshots replayed from disk.
+    Disk(DiskFrames),
+}
+
+impl FrameSource {
+    /// Replay screenshots from a PNG file or a directory of PNG files.
+    pub fn from_path<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::Disk(DiskFrames::open(path)?))
+    }
+
+    pub fn next_frame(&mut self) -> Result<ImageBuffer, FrameError> {
+        match self {
+            FrameSource::Window(0) => Err(FrameError::Unavailable),
+            FrameSource::Window(window_id) => {
+                let provider =
+                    libscreenshot::get_window_capture_provider().ok_or(FrameError::Unavailable)?;
+                provider
+                    .capture_window(*window_id)
+                    .map_err(|_| FrameError::CaptureFailed)
+            }
+            FrameSource::Disk(frames) => frames.next_frame(),
+        }
+    }
+
+    /// Whether a disk sequence has no frames left. Live sources never run out.
+    pub fn is_exhausted(&self) -> bool {
+        match self {
+            FrameSource::Window(_) => false,
+            FrameSource::Disk(frames) => frames.is_exhausted(),
+        }
+    }
+}
+
+impl fmt::Display for FrameSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameSource::Window(window_id) => write!(f, "window {}", window_id),
+            FrameSource::Disk(frames) => match frames.current_path() {
+                Some(path) => write!(f, "{}", path.display()),
+                None => write!(f, "{} frames on disk", frames.len()),
+            },
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use libscreenshot::ImageBuffer;
+use rayon::{
+    iter::{IndexedParallelIterator, ParallelIterator},
+    slice::ParallelSlice,
+};
+
+use super::{Color, ImageAnalyzer};
+use crate::{
+    data::{Point, PointCloud},
+    ipc::HudRegion,
+    platform::IGNORE_AREA_TOP,
+};
+
+/// Reference colors looked for in a region of the frame.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    colors: Vec<Color>,
+    tolerance: u8,
+    min_x: u32,
+    min_y: u32,
+    /// Exclusive, 0 means the right edge of the frame
+    max_x: u32,
+    /// Inclusive, 0 means the bottom edge of the frame
+    max_y: u32,
+    excluded: Option<HudRegion>,
+    /// Only every `downscale`th pixel of every `downscale`th row is scanned, 1 scans them all
+    downscale: u32,
+}
+
+impl Detector {
+    /// Look for `colors` in the whole frame.
+    pub fn new(colors: Vec<Color>, tolerance: u8) -> Self {
+        Self {
+            colors,
+            tolerance,
+            min_x: 0,
+            min_y: 0,
+            max_x: 0,
+            max_y: 0,
+            excluded: None,
+            downscale: 1,
+        }
+    }
+
+    /// Only look in the given region, same bounds as `ImageAnalyzer::pixel_detection`.
+    pub fn in_region(mut self, min_x: u32, min_y: u32, max_x: u32, max_y: u32) -> Self {
+        self.min_x = min_x;
+        self.min_y = min_y;
+        self.max_x = max_x;
+        self.max_y = max_y;
+        self
+    }
+
+    /// Skip the pixels of `region`, e.g. HUD elements sharing a color with what we look for.
+    pub fn excluding(mut self, region: HudRegion) -> Self {
+        self.excluded = Some(region);
+        self
+    }
+
+    /// Scan a `factor` times smaller grid first, then every pixel around the hits. Only suited
+    /// to shapes larger than `factor` in both directions, e.g. mob nameplates.
+    pub fn downscaled(mut self, factor: u32) -> Self {
+        self.downscale = factor.max(1);
+        self
+    }
+
+    fn roi(&self, width: u32, height: u32, ignore_area_bottom: u32) -> Roi {
+        let max_x = if self.max_x == 0 { width } else { self.max_x };
+        let max_y = if self.max_y == 0 { height } else { self.max_y };
+        let last_row = height.checked_sub(ignore_area_bottom).unwrap_or(height);
+        Roi {
+            min_x: self.min_x,
+            min_y: self.min_y.max(IGNORE_AREA_TOP + 1),
+            max_x: max_x.min(width),
+            max_y: max_y.min(last_row).min(height.saturating_sub(1)),
+        }
+    }
+
+    fn is_sampled(&self, coord: u32) -> bool {
+        coord % self.downscale == 0
+    }
+
+    fn matches(&self, x: u32, y: u32, px: &[u8; 4]) -> bool {
+        !self
+            .excluded
+            .map_or(false, |excluded| excluded.contains(x, y))
+            && self
+                .colors
+                .iter()
+                .any(|color| ImageAnalyzer::pixel_matches(px, &color.refs, self.tolerance))
+    }
+
+    /// Scan every pixel around the hits of the downscaled pass.
+    fn refine(&self, image: &ImageBuffer, roi: Roi, coarse: &[Point]) -> Vec<Point> {
+        let step = self.downscale;
+        let mut points = PointCloud::new(coarse)
+            .connected_components(step, step)
+            .into_iter()
+            .flat_map(|cluster| {
+                let bounds = cluster.to_bounds();
+                let around = Roi {
+                    min_x: bounds.x.saturating_sub(step).max(roi.min_x),
+                    min_y: bounds.y.saturating_sub(step).max(roi.min_y),
+                    max_x: (bounds.x + bounds.w + step + 1).min(roi.max_x),
+                    max_y: (bounds.y + bounds.h + step).min(roi.max_y),
+                };
+                (around.min_y..=around.max_y)
+                    .flat_map(move |y| (around.min_x..around.max_x).map(move |x| Point::new(x, y)))
+            })
+            .filter(|point| {
+                let px = image.get_pixel(point.x, point.y).0;
+                px[3] == 255 && self.matches(point.x, point.y, &px)
+            })
+            .collect::<Vec<_>>();
+
+        // Regions around neighbouring clusters may overlap
+        points.sort_by_key(|point| (point.y, point.x));
+        points.dedup();
+        points
+    }
+}
+
+/// Part of the frame a detector looks at, `max_x` is exclusive and `max_y` inclusive.
+#[derive(Debug, Clone, Copy)]
+struct Roi {
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+}
+
+impl Roi {
+    fn is_empty(&self) -> bool {
+        self.min_x >= self.max_x || self.min_y > self.max_y
+    }
+
+    fn union(self, other: Roi) -> Roi {
+        Roi {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+}
+
+/// Walks a frame once and hands every pixel to all detectors whose region contains it.
+#[derive(Debug, Clone)]
+pub struct FrameScanner<K> {
+    detectors: Vec<(K, Detector)>,
+}
+
+impl<K> Default for FrameScanner<K> {
+    fn default() -> Self {
+        Self { detectors: vec![] }
+    }
+}
+
+impl<K: Copy + PartialEq + Sync> FrameScanner<K> {
+    pub fn register(&mut self, key: K, detector: Detector) {
+        self.detectors.push((key, detector));
+    }
+
+    /// Collect the matching pixels of every detector. Only the union of the detector regions
+    /// is walked, the top `IGNORE_AREA_TOP` rows and the bottom `ignore_area_bottom` rows are
+    /// skipped.
+    pub fn scan(&self, image: &ImageBuffer, ignore_area_bottom: u32) -> FrameScan<K> {
+        let detectors = &self.detectors;
+        let rois = detectors
+            .iter()
+            .map(|(_, detector)| detector.roi(image.width(), image.height(), ignore_area_bottom))
+            .collect::<Vec<_>>();
+        let crop = rois
+            .iter()
+            .copied()
+            .filter(|roi| !roi.is_empty())
+            .reduce(Roi::union);
+
+        let mut points = match crop {
+            Some(crop) => {
+                let rois = &rois;
+                scan_rows(image, crop, detectors.len(), |y, row, points| {
+                    let active = detectors
+                        .iter()
+                        .zip(rois)
+                        .enumerate()
+                        .filter(|(_, ((_, detector), roi))| {
+                            !roi.is_empty()
+                                && y >= roi.min_y
+                                && y <= roi.max_y
+                                && detector.is_sampled(y)
+                        })
+                        .map(|(index, ((_, detector), roi))| (index, detector, roi))
+                        .collect::<Vec<_>>();
+                    if active.is_empty() {
+                        return;
+                    }
+
+                    for (x, px) in (crop.min_x..).zip(row.chunks_exact(4)) {
+                        let px = [px[0], px[1], px[2], px[3]];
+                        // Nothing past a transparent pixel is part of the client
+                        if px[3] != 255 {
+                            return;
+                        }
+                        for &(index, detector, roi) in &active {
+                            if x >= roi.min_x
+                                && x < roi.max_x
+                                && detector.is_sampled(x)
+                                && detector.matches(x, y, &px)
+                            {
+                                points[index].push(Point::new(x, y));
+                            }
+                        }
+                    }
+                })
+            }
+            None => vec![Vec::new(); detectors.len()],
+        };
+
+        for (points, ((_, detector), roi)) in points.iter_mut().zip(detectors.iter().zip(&rois)) {
+            if detector.downscale > 1 && !points.is_empty() {
+                *points = detector.refine(image, *roi, points);
+            }
+        }
+
+        FrameScan {
+            detections: detectors
+                .iter()
+                .map(|(key, _)| *key)
+                .zip(points.into_iter().map(PointCloud::new))
+                .collect(),
+        }
+    }
+}
+
+/// Call `scan_row` with the y coordinate and RGBA bytes of every row of `image` within `crop`
+/// in parallel. Each thread pushes into its own `buffers` point lists, they are concatenated in
+/// row order so the result is the same as a sequential scan.
+fn scan_rows<F>(image: &ImageBuffer, crop: Roi, buffers: usize, scan_row: F) -> Vec<Vec<Point>>
+where
+    F: Fn(u32, &[u8], &mut [Vec<Point>]) + Sync,
+{
+    let row_len = image.width() as usize * 4;
+    let rows = &image.as_raw()[crop.min_y as usize * row_len..(crop.max_y as usize + 1) * row_len];
+    let columns = crop.min_x as usize * 4..crop.max_x as usize * 4;
+    rows.par_chunks(row_len)
+        .enumerate()
+        .fold(
+            || vec![Vec::new(); buffers],
+            |mut points, (y, row)| {
+                scan_row(crop.min_y + y as u32, &row[columns.clone()], &mut points);
+                points
+            },
+        )
+        .reduce(
+            || vec![Vec::new(); buffers],
+            |mut points, other| {
+                for (points, mut other) in points.iter_mut().zip(other) {
+                    points.append(&mut other);
+                }
+                points
+            },
+        )
+}
+
+/// Pixels found by each detector of a `FrameScanner` in one frame.
+#[derive(Debug, Clone)]
+pub struct FrameScan<K> {
+    detections: Vec<(K, PointCloud)>,
+}
+
+impl<K: PartialEq> FrameScan<K> {
+    pub fn get(&self, key: K) -> Option<&PointCloud> {
+        self.detections
+            .iter()
+            .find(|(detection_key, _)| *detection_key == key)
+            .map(|(_, cloud)| cloud)
+    }
+
+    pub fn remove(&mut self, key: K) -> Option<PointCloud> {
+        let index = self
+            .detections
+            .iter()
+            .position(|(detection_key, _)| *detection_key == key)?;
+        Some(self.detections.swap_remove(index).1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+    use libscreenshot::ImageBuffer;
+
+    use super::{Detector, FrameScanner};
+    use crate::{
+        data::{Bounds, Point, PointCloud},
+        image_analyzer::Color,
+        ipc::HudRegion,
+    };
+
+    #[test]
+    fn test_scan() {
+        let red = Color::new(200, 0, 0);
+        let mut image = ImageBuffer::from_pixel(40, 20, Rgba([0, 0, 0, 255]));
+        for x in 5..15 {
+            image.put_pixel(x, 10, Rgba([200, 0, 0, 255]));
+            image.put_pixel(x, 2, Rgba([200, 0, 0, 255]));
+        }
+        image.put_pixel(30, 10, Rgba([202, 1, 0, 255]));
+
+        let mut scanner = FrameScanner::default();
+        scanner.register("anywhere", Detector::new(vec![red], 0));
+        scanner.register(
+            "tolerant",
+            Detector::new(vec![red], 2).in_region(20, 0, 0, 0),
+        );
+        scanner.register(
+            "not_top",
+            Detector::new(vec![red], 0).excluding(HudRegion::new(0, 0, 39, 5)),
+        );
+        scanner.register("above_bottom", Detector::new(vec![red], 0));
+
+        let scan = scanner.scan(&image, 0);
+        let bounds = |key| scan.get(key).map(PointCloud::to_bounds);
+        assert_eq!(bounds("anywhere"), Some(Bounds::new(5, 2, 9, 8)));
+        assert_eq!(bounds("tolerant"), Some(Bounds::new(30, 10, 0, 0)));
+        assert_eq!(bounds("not_top"), Some(Bounds::new(5, 10, 9, 0)));
+        assert_eq!(bounds("missing"), None);
+
+        // Points are in reading order no matter how rows were split between threads
+        let expected = (5..15)
+            .map(|x| Point::new(x, 2))
+            .chain((5..15).map(|x| Point::new(x, 10)))
+            .collect::<Vec<_>>();
+        for _ in 0..10 {
+            assert_eq!(
+                scanner.scan(&image, 0).get("anywhere"),
+                Some(&PointCloud::new(&expected))
+            );
+        }
+
+        // Rows of the ignored bottom area are skipped
+        let scan = scanner.scan(&image, 15);
+        assert_eq!(
+            scan.get("above_bottom").map(PointCloud::to_bounds),
+            Some(Bounds::new(5, 2, 9, 0))
+        );
+    }
+
+    #[test]
+    fn test_downscaled_scan() {
+        let red = Color::new(200, 0, 0);
+        let mut image = ImageBuffer::from_pixel(40, 20, Rgba([0, 0, 0, 255]));
+        // Odd coordinates only, never sampled on its own
+        image.put_pixel(3, 3, Rgba([200, 0, 0, 255]));
+        for x in 9..21 {
+            for y in 9..12 {
+                image.put_pixel(x, y, Rgba([200, 0, 0, 255]));
+            }
+        }
+
+        let mut scanner = FrameScanner::default();
+        scanner.register("full", Detector::new(vec![red], 0));
+        scanner.register("downscaled", Detector::new(vec![red], 0).downscaled(2));
+        let scan = scanner.scan(&image, 0);
+        let full = scan.get("full").unwrap();
+        assert_eq!(full.to_bounds(), Bounds::new(3, 3, 17, 8));
+        assert_eq!(
+            scan.get("downscaled").map(PointCloud::to_bounds),
+            Some(Bounds::new(9, 9, 11, 2))
+        );
+    }
+}
@@ -1,7 +1,13 @@
 mod bot_config;
 mod frontend_info;
+mod hud_layout;
 
 pub use self::{
-    bot_config::{BotConfig, BotMode, FarmingConfig, ShoutConfig, Slot, SlotType, SupportConfig},
-    frontend_info::FrontendInfo,
+    bot_config::{
+        has_errors, BotConfig, BotMode, BuffIcon, ConfigError, FarmingConfig, RotationPhase,
+        RotationSlot, ShoutConfig, SkillRotation, Slot, SlotBar, SlotType, SlotsState,
+        StatusBarColors, SupportConfig,
+    },
+    frontend_info::{FrontendInfo, SlotUsage},
+    hud_layout::{HudLayout, HudRegion},
 };
@@ -1,8 +1,27 @@
-use std::{fmt, fs::File, time::Instant};
+mod migration;
+mod validation;
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+use super::HudLayout;
+use crate::image_analyzer::{CalibrationTarget, ColorCalibration, IconTemplate};
+
+use self::migration::CURRENT_VERSION;
+pub use self::{migration::ConfigError, validation::has_errors};
+
+/// Nameplates dilated further merge with their neighbors, and the neighborhood scanned around
+/// each pixel grows with the square of the dilation
+const MAX_MOBS_NAME_DILATION: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum SlotType {
     Unused,
     Food,
@@ -17,6 +36,25 @@ pub enum SlotType {
     RezSkill,
     Flying,
 }
+
+impl SlotType {
+    /// Skills with a cooldown overlay on the slot bar
+    pub fn is_skill(&self) -> bool {
+        matches!(
+            self,
+            SlotType::AttackSkill | SlotType::BuffSkill | SlotType::HealSkill
+        )
+    }
+
+    /// Items used up from a stack, as opposed to skills and pets
+    pub fn is_consumable(&self) -> bool {
+        matches!(
+            self,
+            SlotType::Food | SlotType::Pill | SlotType::MpRestorer | SlotType::FpRestorer
+        )
+    }
+}
+
 impl fmt::Display for SlotType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -34,6 +72,16 @@ impl fmt::Display for SlotType {
     }
 }
 
+/// What the bot knows about every slot when picking one to press.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SlotsState {
+    /// When each slot was last pressed, cleared once it is ready again
+    pub last_usage: [[Option<Instant>; 10]; 9],
+    pub usage_count: [[u32; 10]; 9],
+    /// Consumable slots the action bar shows as empty
+    pub empty: [[bool; 10]; 9],
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SlotBar {
     slots: Option<[Slot; 10]>,
@@ -63,7 +111,7 @@ impl SlotBar {
         &self,
         slot_type: SlotType,
         threshold: Option<u32>,
-        last_slots_usage: [[Option<Instant>; 10]; 9],
+        slots_state: &SlotsState,
         slot_bar_index: usize,
     ) -> Option<(usize, usize)> {
         self.slots()
@@ -73,7 +121,11 @@ impl SlotBar {
                 slot.slot_type == slot_type
                     && slot.slot_enabled
                     && slot.slot_threshold.unwrap_or(100) >= threshold.unwrap_or(0)
-                    && last_slots_usage[slot_bar_index][*index].is_none()
+                    && slots_state.last_usage[slot_bar_index][*index].is_none()
+                    && !slot.is_exhausted(slots_state.usage_count[slot_bar_index][*index])
+                    && !(slot_type.is_consumable() && slots_state.empty[slot_bar_index][*index])
+                    // Buffs with a duration are recast by their own timer
+                    && slot.buff_duration().is_none()
             })
             .min_by(|x, y| x.1.slot_threshold.cmp(&y.1.slot_threshold))
             //.choose(rng)
@@ -87,6 +139,10 @@ pub struct Slot {
     slot_cooldown: Option<u32>,
     slot_threshold: Option<u32>,
     slot_enabled: bool,
+    /// Uses until the stack is assumed empty, no limit if unset or 0
+    slot_max_uses: Option<u32>,
+    /// Milliseconds a buff lasts, buffs without a duration are cast every `interval_between_buffs`
+    slot_buff_duration: Option<u32>,
 }
 
 impl Default for Slot {
@@ -96,11 +152,40 @@ impl Default for Slot {
             slot_cooldown: None,
             slot_threshold: None,
             slot_enabled: true,
+            slot_max_uses: None,
+            slot_buff_duration: None,
         }
     }
 }
 
 impl Slot {
+    pub fn slot_type(&self) -> SlotType {
+        self.slot_type
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.slot_enabled
+    }
+
+    pub fn max_uses(&self) -> Option<u32> {
+        self.slot_max_uses.filter(|max_uses| *max_uses > 0)
+    }
+
+    /// How long the buff of a buff skill slot lasts, `None` if it has no duration
+    pub fn buff_duration(&self) -> Option<Duration> {
+        if self.slot_type != SlotType::BuffSkill {
+            return None;
+        }
+        self.slot_buff_duration
+            .filter(|duration| *duration > 0)
+            .map(|duration| Duration::from_millis(duration.into()))
+    }
+
+    /// Whether the slot was used `uses` times and is assumed empty
+    pub fn is_exhausted(&self, uses: u32) -> bool {
+        self.max_uses().map_or(false, |max_uses| uses >= max_uses)
+    }
+
     pub fn get_slot_cooldown(&self) -> Option<u32> {
         let cooldown = self.slot_cooldown;
         if cooldown.is_some() {
@@ -128,6 +213,84 @@ impl ToString for BotMode {
     }
 }
 
+/// Calibrated status bar colors, the built-in palette is used when unset
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusBarColors {
+    pub hp: Option<Vec<[u8; 3]>>,
+    pub mp: Option<Vec<[u8; 3]>>,
+    pub fp: Option<Vec<[u8; 3]>>,
+    pub hp_tolerance: Option<u8>,
+    pub mp_tolerance: Option<u8>,
+    pub fp_tolerance: Option<u8>,
+}
+
+/// Icon shown in the buff area while the buff of a slot is active
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuffIcon {
+    pub slot_bar_index: usize,
+    pub slot_index: usize,
+    pub icon: IconTemplate,
+}
+
+/// Slot pressed by a step of a skill rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationSlot {
+    pub slot_bar_index: usize,
+    pub slot_index: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationPhase {
+    /// Used once per fight, in order
+    Opener,
+    /// Used in a loop until the finisher takes over
+    Filler,
+    /// Used in a loop once the target HP is low enough
+    Finisher,
+}
+
+/// Ordered attack skills used while fighting instead of the lowest threshold attack skill.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SkillRotation {
+    opener: Option<Vec<RotationSlot>>,
+    filler: Option<Vec<RotationSlot>>,
+    finisher: Option<Vec<RotationSlot>>,
+    /// Target HP percent from which the finisher is used
+    finisher_target_hp: Option<u32>,
+}
+
+impl SkillRotation {
+    pub fn steps(&self, phase: RotationPhase) -> &[RotationSlot] {
+        match phase {
+            RotationPhase::Opener => &self.opener,
+            RotationPhase::Filler => &self.filler,
+            RotationPhase::Finisher => &self.finisher,
+        }
+        .as_deref()
+        .unwrap_or(&[])
+    }
+
+    pub fn finisher_target_hp(&self) -> u32 {
+        self.finisher_target_hp.unwrap_or(30)
+    }
+
+    /// Number of steps of every phase
+    pub fn len(&self) -> usize {
+        [
+            RotationPhase::Opener,
+            RotationPhase::Filler,
+            RotationPhase::Finisher,
+        ]
+        .into_iter()
+        .map(|phase| self.steps(phase).len())
+        .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct FarmingConfig {
     /// Slot configuration
@@ -147,20 +310,97 @@ pub struct FarmingConfig {
     passive_tolerence: Option<u8>,
     aggressive_mobs_colors: Option<[Option<u8>; 3]>,
     aggressive_tolerence: Option<u8>,
+    status_bar_colors: Option<StatusBarColors>,
 
     obstacle_avoidance_cooldown: Option<u64>,
     obstacle_avoidance_max_try: Option<u32>,
 
     min_mobs_name_width: Option<u32>,
     max_mobs_name_width: Option<u32>,
+    /// Pixels a nameplate pixel is grown by before splitting names apart, see `PointCloud::connected_components`
+    mobs_name_dilation_x: Option<u32>,
+    mobs_name_dilation_y: Option<u32>,
+    /// Nameplates are first looked for on a grid this many times smaller, 1 scans every pixel
+    mobs_scan_downscale: Option<u32>,
 
     min_hp_attack: Option<u32>,
     on_death_disconnect: Option<bool>,
     interval_between_buffs: Option<u64>,
     mobs_timeout: Option<u64>,
+    /// Skip consumable slots that look empty on the action bar
+    empty_slots_detection: Option<bool>,
+    /// Read skill cooldowns from the action bar instead of the configured values
+    cooldown_detection: Option<bool>,
+    skill_rotation: Option<SkillRotation>,
+    /// Look for the icons of buffs with a duration, their timer is used when it can't tell
+    buff_detection: Option<bool>,
+    buff_icons: Option<Vec<BuffIcon>>,
 }
 
 impl FarmingConfig {
+    /// Store colors derived by the calibration wizard.
+    pub fn apply_calibration(&mut self, calibration: &ColorCalibration) {
+        let color = calibration
+            .colors
+            .first()
+            .map(|color| [Some(color[0]), Some(color[1]), Some(color[2])]);
+        let colors = Some(calibration.colors.clone());
+        let tolerance = Some(calibration.tolerance);
+
+        match calibration.target {
+            CalibrationTarget::PassiveMob => {
+                self.passive_mobs_colors = color;
+                self.passive_tolerence = tolerance;
+            }
+            CalibrationTarget::AggressiveMob => {
+                self.aggressive_mobs_colors = color;
+                self.aggressive_tolerence = tolerance;
+            }
+            CalibrationTarget::HpBar => {
+                let bar_colors = self.status_bar_colors.get_or_insert_with(Default::default);
+                bar_colors.hp = colors;
+                bar_colors.hp_tolerance = tolerance;
+            }
+            CalibrationTarget::MpBar => {
+                let bar_colors = self.status_bar_colors.get_or_insert_with(Default::default);
+                bar_colors.mp = colors;
+                bar_colors.mp_tolerance = tolerance;
+            }
+            CalibrationTarget::FpBar => {
+                let bar_colors = self.status_bar_colors.get_or_insert_with(Default::default);
+                bar_colors.fp = colors;
+                bar_colors.fp_tolerance = tolerance;
+            }
+        }
+    }
+
+    pub fn status_bar_colors(&self) -> StatusBarColors {
+        self.status_bar_colors.clone().unwrap_or_default()
+    }
+
+    pub fn empty_slots_detection(&self) -> bool {
+        self.empty_slots_detection.unwrap_or(false)
+    }
+
+    pub fn cooldown_detection(&self) -> bool {
+        self.cooldown_detection.unwrap_or(false)
+    }
+
+    pub fn buff_detection(&self) -> bool {
+        self.buff_detection.unwrap_or(false)
+    }
+
+    pub fn buff_icons(&self) -> &[BuffIcon] {
+        self.buff_icons.as_deref().unwrap_or(&[])
+    }
+
+    /// Configured skill rotation, `None` if it has no step
+    pub fn skill_rotation(&self) -> Option<&SkillRotation> {
+        self.skill_rotation
+            .as_ref()
+            .filter(|rotation| !rotation.is_empty())
+    }
+
     pub fn mobs_timeout(&self) -> u128 {
         self.mobs_timeout.unwrap_or(0).into()
     }
@@ -193,6 +433,22 @@ impl FarmingConfig {
         self.max_mobs_name_width.unwrap_or(180)
     }
 
+    /// Horizontal and vertical nameplate dilation, spaces between words are about 4px wide.
+    pub fn mobs_name_dilation(&self) -> (u32, u32) {
+        (
+            self.mobs_name_dilation_x
+                .unwrap_or(2)
+                .min(MAX_MOBS_NAME_DILATION),
+            self.mobs_name_dilation_y
+                .unwrap_or(1)
+                .min(MAX_MOBS_NAME_DILATION),
+        )
+    }
+
+    pub fn mobs_scan_downscale(&self) -> u32 {
+        self.mobs_scan_downscale.unwrap_or(1).max(1)
+    }
+
     pub fn min_hp_attack(&self) -> u32 {
         self.min_hp_attack.unwrap_or(0)
     }
@@ -243,15 +499,11 @@ impl FarmingConfig {
         &self,
         slot_type: SlotType,
         threshold: Option<u32>,
-        last_slots_usage: [[Option<Instant>; 10]; 9],
+        slots_state: &SlotsState,
     ) -> Option<(usize, usize)> {
         for n in 0..9 {
-            let found_index = self.slot_bars()[n].get_usable_slot_index(
-                slot_type,
-                threshold,
-                last_slots_usage,
-                n,
-            );
+            let found_index =
+                self.slot_bars()[n].get_usable_slot_index(slot_type, threshold, slots_state, n);
             if let Some(found_index) = found_index {
                 return Some(found_index);
             }
@@ -274,9 +526,21 @@ pub struct SupportConfig {
     obstacle_avoidance_cooldown: Option<u64>,
     on_death_disconnect: Option<bool>,
     interval_between_buffs: Option<u64>,
+    /// Skip consumable slots that look empty on the action bar
+    empty_slots_detection: Option<bool>,
+    /// Read skill cooldowns from the action bar instead of the configured values
+    cooldown_detection: Option<bool>,
 }
 
 impl SupportConfig {
+    pub fn empty_slots_detection(&self) -> bool {
+        self.empty_slots_detection.unwrap_or(false)
+    }
+
+    pub fn cooldown_detection(&self) -> bool {
+        self.cooldown_detection.unwrap_or(false)
+    }
+
     pub fn interval_between_buffs(&self) -> u128 {
         self.interval_between_buffs.unwrap_or(2000).into()
     }
@@ -308,15 +572,11 @@ impl SupportConfig {
         &self,
         slot_type: SlotType,
         threshold: Option<u32>,
-        last_slots_usage: [[Option<Instant>; 10]; 9],
+        slots_state: &SlotsState,
     ) -> Option<(usize, usize)> {
         for n in 0..9 {
-            let found_index = self.slot_bars()[n].get_usable_slot_index(
-                slot_type,
-                threshold,
-                last_slots_usage,
-                n,
-            );
+            let found_index =
+                self.slot_bars()[n].get_usable_slot_index(slot_type, threshold, slots_state, n);
             if let Some(found_index) = found_index {
                 return Some(found_index);
             }
@@ -341,8 +601,12 @@ impl ShoutConfig {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BotConfig {
+    /// Schema version, see `migration`
+    #[serde(default)]
+    version: u32,
+
     /// Change id to sync changes between frontend and backend
     change_id: u64,
 
@@ -355,6 +619,32 @@ pub struct BotConfig {
     farming_config: FarmingConfig,
     support_config: SupportConfig,
     shout_config: ShoutConfig,
+
+    /// Client HUD layout, defaults to the 800x600 client window
+    hud_layout: Option<HudLayout>,
+
+    /// Number of ticks kept by the recorder, 0 disables it
+    recorder_frames: Option<usize>,
+
+    /// Target main loop ticks per second, 0 runs as fast as capture allows
+    tick_rate: Option<u32>,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            change_id: 0,
+            is_running: false,
+            mode: None,
+            farming_config: FarmingConfig::default(),
+            support_config: SupportConfig::default(),
+            shout_config: ShoutConfig::default(),
+            hud_layout: None,
+            recorder_frames: None,
+            tick_rate: None,
+        }
+    }
 }
 
 impl BotConfig {
@@ -375,6 +665,15 @@ impl BotConfig {
         self
     }
 
+    /// Take the running state, mode and change id of `other`, e.g. a change refused for its
+    /// errors still engages, disengages or switches the bot.
+    pub fn with_state_of(mut self, other: &BotConfig) -> Self {
+        self.is_running = other.is_running;
+        self.mode = other.mode.clone();
+        self.change_id = other.change_id;
+        self
+    }
+
     pub fn farming_config(&self) -> &FarmingConfig {
         &self.farming_config
     }
@@ -387,15 +686,47 @@ impl BotConfig {
         &self.shout_config
     }
 
+    pub fn hud_layout(&self) -> HudLayout {
+        self.hud_layout.clone().unwrap_or_default()
+    }
+
+    pub fn recorder_frames(&self) -> usize {
+        self.recorder_frames.unwrap_or(0)
+    }
+
+    pub fn tick_rate(&self) -> u32 {
+        self.tick_rate.unwrap_or(0)
+    }
+
+    /// Type of a slot pressed in `mode`, the auto shout mode has no slots.
+    pub fn slot_type(
+        &self,
+        mode: &BotMode,
+        slot_bar_index: usize,
+        slot_index: usize,
+    ) -> Option<SlotType> {
+        let slot_bars = match mode {
+            BotMode::Farming => self.farming_config().slot_bars(),
+            BotMode::Support => self.support_config().slot_bars(),
+            BotMode::AutoShout => return None,
+        };
+        slot_bars
+            .get(slot_bar_index)?
+            .slots()
+            .get(slot_index)
+            .map(Slot::slot_type)
+    }
+
     pub fn mode(&self) -> Option<BotMode> {
         self.mode.clone()
     }
 
     /// Serialize config to disk
-    pub fn serialize(&self, path: String) {
+    pub fn serialize(&self, path: &Path) {
         let config = {
             let mut config = self.clone();
             config.is_running = false;
+            config.version = CURRENT_VERSION;
             config
         };
         if let Ok(mut file) = File::create(path) {
@@ -403,12 +734,34 @@ impl BotConfig {
         }
     }
 
-    /// Deserialize config from disk
-    pub fn deserialize_or_default(path: String) -> Self {
-        if let Ok(mut file) = File::open(path) {
-            serde_json::from_reader::<_, BotConfig>(&mut file).unwrap_or_default()
-        } else {
-            Self::default()
-        }
+    /// Deserialize config from disk, migrating older schema versions. A missing file yields the
+    /// default config.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(ConfigError::Io(err)),
+        };
+        let value =
+            serde_json::from_reader(io::BufReader::new(file)).map_err(ConfigError::Parse)?;
+        Self::from_value(value)
+    }
+
+    /// Deserialize a config of any known schema version.
+    pub fn from_value(value: serde_json::Value) -> Result<Self, ConfigError> {
+        serde_json::from_value(migration::migrate(value)?).map_err(ConfigError::Parse)
+    }
+
+    /// Keep a copy of a config file that could not be loaded before it gets overwritten.
+    pub fn backup(path: &Path) -> io::Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default();
+        let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+        backup_name.push(format!(".backup_{}", timestamp));
+        let backup_path = path.with_file_name(backup_name);
+        fs::copy(path, &backup_path)?;
+        Ok(backup_path)
     }
 }
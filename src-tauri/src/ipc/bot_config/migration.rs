@@ -0,0 +1,177 @@
+use serde_json::{json, Map, Value};
+
+/// Schema version written by this build. Bump it and add a migration to `MIGRATIONS` whenever a
+/// change to `BotConfig` would make older files unreadable.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a version `n` config to version `n + 1`
+const MIGRATIONS: [fn(&mut Map<String, Value>); CURRENT_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2];
+
+/// Reason why a config file could not be read.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    /// The file is not valid JSON or does not match the schema after migrating.
+    Parse(serde_json::Error),
+    /// The config was written by a newer version of the bot.
+    UnsupportedVersion(u32),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Parse(err) => write!(f, "{}", err),
+            ConfigError::UnsupportedVersion(version) => write!(
+                f,
+                "config version {} is newer than the supported version {}",
+                version, CURRENT_VERSION
+            ),
+        }
+    }
+}
+
+/// Upgrade a raw config of any known version to `CURRENT_VERSION`.
+pub fn migrate(mut value: Value) -> Result<Value, ConfigError> {
+    let config = match value.as_object_mut() {
+        Some(config) => config,
+        // Let serde report what is wrong
+        None => return Ok(value),
+    };
+
+    // Files written before the version field existed are version 0
+    let version = config.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > CURRENT_VERSION {
+        return Err(ConfigError::UnsupportedVersion(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(config);
+    }
+    config.insert("version".to_string(), json!(CURRENT_VERSION));
+
+    Ok(value)
+}
+
+/// Slots used to be a single bar stored in `slots`, and `slot_enabled` did not exist.
+fn migrate_v0_to_v1(config: &mut Map<String, Value>) {
+    for behavior in ["farming_config", "support_config"] {
+        let behavior_config = match config.get_mut(behavior).and_then(Value::as_object_mut) {
+            Some(behavior_config) => behavior_config,
+            None => continue,
+        };
+
+        if let Some(slots) = behavior_config.remove("slots") {
+            if !behavior_config.contains_key("slot_bars") && slots.is_array() {
+                let unused_slot = json!({ "slot_type": "Unused", "slot_enabled": true });
+                let mut slot_bars = vec![json!({ "slots": slots })];
+                slot_bars.resize(9, json!({ "slots": vec![unused_slot; 10] }));
+                behavior_config.insert("slot_bars".to_string(), Value::Array(slot_bars));
+            }
+        }
+
+        let slot_bars = behavior_config
+            .get_mut("slot_bars")
+            .and_then(Value::as_array_mut);
+        for slot_bar in slot_bars.into_iter().flatten() {
+            let slots = slot_bar.get_mut("slots").and_then(Value::as_array_mut);
+            for slot in slots.into_iter().flatten().filter_map(Value::as_object_mut) {
+                slot.entry("slot_enabled").or_insert(json!(true));
+            }
+        }
+    }
+}
+
+/// The tick rate and recorder are used in every mode, they moved out of `farming_config`.
+fn migrate_v1_to_v2(config: &mut Map<String, Value>) {
+    let farming_config = match config
+        .get_mut("farming_config")
+        .and_then(Value::as_object_mut)
+    {
+        Some(farming_config) => farming_config,
+        None => return,
+    };
+
+    let moved = ["tick_rate", "recorder_frames"]
+        .into_iter()
+        .filter_map(|key| Some((key, farming_config.remove(key)?)))
+        .collect::<Vec<_>>();
+    for (key, value) in moved {
+        config.entry(key).or_insert(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{migrate, ConfigError, CURRENT_VERSION};
+    use crate::ipc::{BotConfig, SlotType, SlotsState};
+
+    #[test]
+    fn test_migrate_v0() {
+        let slot = json!({ "slot_type": "Food", "slot_cooldown": 500, "slot_threshold": 60 });
+        let slot_bar = json!({ "slots": vec![slot.clone(); 10] });
+        let v0 = json!({
+            "change_id": 3,
+            "is_running": false,
+            "mode": "Farming",
+            "farming_config": { "slots": vec![slot; 10] },
+            "support_config": { "slot_bars": vec![slot_bar; 9] },
+            "shout_config": {},
+        });
+        // Version 0 configs do not match the current schema
+        assert!(serde_json::from_value::<BotConfig>(v0.clone()).is_err());
+
+        let migrated = migrate(v0).unwrap();
+        assert_eq!(migrated["version"], CURRENT_VERSION);
+        let config = serde_json::from_value::<BotConfig>(migrated).unwrap();
+        assert_eq!(config.farming_config().slot_bars().len(), 9);
+        assert_eq!(
+            config.farming_config().slot_index(SlotType::Food),
+            Some((0, 0))
+        );
+        assert_eq!(config.farming_config().get_slot_cooldown(0, 9), Some(500));
+        assert_eq!(config.farming_config().slot_index(SlotType::Pill), None);
+        // Slots are enabled unless disabled explicitly
+        assert_eq!(
+            config.support_config().get_usable_slot_index(
+                SlotType::Food,
+                None,
+                &SlotsState::default()
+            ),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        let v1 = json!({
+            "version": 1,
+            "change_id": 0,
+            "is_running": false,
+            "mode": "Support",
+            "farming_config": { "tick_rate": 10, "recorder_frames": 50 },
+            "support_config": {},
+            "shout_config": {},
+        });
+
+        let migrated = migrate(v1).unwrap();
+        assert_eq!(migrated["farming_config"], json!({}));
+        let config = serde_json::from_value::<BotConfig>(migrated).unwrap();
+        assert_eq!(config.tick_rate(), 10);
+        assert_eq!(config.recorder_frames(), 50);
+    }
+
+    #[test]
+    fn test_migrate_newer_version() {
+        assert!(matches!(
+            migrate(json!({ "version": CURRENT_VERSION + 1 })),
+            Err(ConfigError::UnsupportedVersion(_))
+        ));
+        // Current configs are left alone
+        let current = serde_json::to_value(BotConfig::default()).unwrap();
+        assert_eq!(migrate(current.clone()).unwrap(), current);
+    }
+}
@@ -0,0 +1,495 @@
+use serde::Serialize;
+
+use super::{
+    BotConfig, BotMode, BuffIcon, FarmingConfig, RotationPhase, SkillRotation, SlotBar, SlotType,
+    SupportConfig, MAX_MOBS_NAME_DILATION,
+};
+use crate::ipc::{HudLayout, HudRegion};
+
+/// Recorded frames are kept in memory, about 2MB each for an 800x600 client
+const MAX_RECORDER_FRAMES: usize = 300;
+
+/// Capturing and analyzing a frame takes longer than the tick budget of faster rates
+const MAX_TICK_RATE: u32 = 30;
+
+/// Nameplate letters are about 5px wide, coarser grids skip whole letters
+const MAX_MOBS_SCAN_DOWNSCALE: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    /// Suspicious but usable, the config is applied.
+    Warning,
+    /// The config is refused.
+    Error,
+}
+
+/// Problem found in a config, `path` points at the offending field, e.g.
+/// `farming_config.slot_bars[0].slots[3].slot_threshold`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigIssue {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Issues(Vec<ConfigIssue>);
+
+impl Issues {
+    fn warn(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, path.into(), message.into());
+    }
+
+    fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, path.into(), message.into());
+    }
+
+    fn push(&mut self, severity: Severity, path: String, message: String) {
+        self.0.push(ConfigIssue {
+            severity,
+            path,
+            message,
+        });
+    }
+}
+
+impl BotConfig {
+    /// Look for values the bot cannot work with. Configs with `Severity::Error` issues should not
+    /// be applied.
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Issues::default();
+        let mode = self.mode();
+        validate_farming(
+            &self.farming_config,
+            mode == Some(BotMode::Farming),
+            &mut issues,
+        );
+        if mode == Some(BotMode::Support) {
+            validate_support(&self.support_config, &mut issues);
+        }
+
+        if mode == Some(BotMode::AutoShout) {
+            if self.shout_config.shout_messages().is_empty() {
+                issues.warn("shout_config.shout_messages", "no message to shout");
+            }
+            if self.shout_config.shout_interval() == 0 {
+                issues.error("shout_config.shout_interval", "must be greater than 0");
+            }
+        }
+
+        if let Some(hud_layout) = &self.hud_layout {
+            validate_hud_layout(hud_layout, &mut issues);
+        }
+
+        if self.tick_rate() > MAX_TICK_RATE {
+            issues.warn(
+                "tick_rate",
+                format!(
+                    "capturing and analyzing a frame rarely takes less than {}ms",
+                    1000 / MAX_TICK_RATE
+                ),
+            );
+        }
+        if self.recorder_frames() > MAX_RECORDER_FRAMES {
+            issues.warn(
+                "recorder_frames",
+                format!(
+                    "more than {} frames use a lot of memory",
+                    MAX_RECORDER_FRAMES
+                ),
+            );
+        }
+
+        issues.0
+    }
+
+    /// Errors of this config at fields where `applied` has none. Configs loaded from disk are
+    /// applied even with errors, refusing only new ones lets the user repair them one at a time.
+    pub fn new_errors(&self, applied: &BotConfig) -> Vec<ConfigIssue> {
+        let applied_errors = applied
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| issue.path)
+            .collect::<Vec<_>>();
+        self.validate()
+            .into_iter()
+            .filter(|issue| {
+                issue.severity == Severity::Error && !applied_errors.contains(&issue.path)
+            })
+            .collect()
+    }
+}
+
+pub fn has_errors(issues: &[ConfigIssue]) -> bool {
+    issues.iter().any(|issue| issue.severity == Severity::Error)
+}
+
+fn validate_farming(config: &FarmingConfig, is_active: bool, issues: &mut Issues) {
+    let path = "farming_config";
+    if config.min_mobs_name_width() > config.max_mobs_name_width() {
+        issues.error(
+            format!("{}.max_mobs_name_width", path),
+            format!(
+                "must not be lower than the min mobs name width ({}px)",
+                config.min_mobs_name_width()
+            ),
+        );
+    }
+    if config.obstacle_avoidance_max_try() == 0 {
+        issues.warn(
+            format!("{}.obstacle_avoidance_max_try", path),
+            "obstacle avoidance is disabled, mobs behind obstacles are abandoned right away",
+        );
+    }
+    if config.min_hp_attack() > 100 {
+        issues.error(format!("{}.min_hp_attack", path), "must be a percentage");
+    }
+    for (name, dilation) in [
+        ("mobs_name_dilation_x", config.mobs_name_dilation_x),
+        ("mobs_name_dilation_y", config.mobs_name_dilation_y),
+    ] {
+        if dilation.map_or(false, |dilation| dilation > MAX_MOBS_NAME_DILATION) {
+            issues.error(
+                format!("{}.{}", path, name),
+                format!("must not be greater than {}px", MAX_MOBS_NAME_DILATION),
+            );
+        }
+    }
+    if config.mobs_scan_downscale() > MAX_MOBS_SCAN_DOWNSCALE {
+        issues.warn(
+            format!("{}.mobs_scan_downscale", path),
+            format!(
+                "more than {} may miss small nameplates",
+                MAX_MOBS_SCAN_DOWNSCALE
+            ),
+        );
+    }
+
+    let colors = config.status_bar_colors();
+    for (name, colors) in [("hp", colors.hp), ("mp", colors.mp), ("fp", colors.fp)] {
+        if colors.map_or(false, |colors| colors.is_empty()) {
+            issues.error(
+                format!("{}.status_bar_colors.{}", path, name),
+                "needs at least one color, calibrate the bar again",
+            );
+        }
+    }
+
+    validate_slot_bars(path, &config.slot_bars(), issues);
+    if is_active && config.slot_index(SlotType::AttackSkill).is_none() {
+        issues.warn(
+            format!("{}.slot_bars", path),
+            "no attack skill slot, mobs will not be attacked",
+        );
+    }
+    if let Some(rotation) = config.skill_rotation() {
+        validate_skill_rotation(path, config, rotation, issues);
+    }
+    validate_buff_icons(path, &config.slot_bars(), config.buff_icons(), issues);
+}
+
+fn validate_skill_rotation(
+    path: &str,
+    config: &FarmingConfig,
+    rotation: &SkillRotation,
+    issues: &mut Issues,
+) {
+    let path = format!("{}.skill_rotation", path);
+    if rotation.finisher_target_hp() > 100 {
+        issues.error(
+            format!("{}.finisher_target_hp", path),
+            "must be a percentage",
+        );
+    }
+    if rotation.steps(RotationPhase::Filler).is_empty() {
+        issues.warn(
+            format!("{}.filler", path),
+            "no filler, the lowest threshold attack skill is used between opener and finisher",
+        );
+    }
+
+    let slot_bars = config.slot_bars();
+    for (name, phase) in [
+        ("opener", RotationPhase::Opener),
+        ("filler", RotationPhase::Filler),
+        ("finisher", RotationPhase::Finisher),
+    ] {
+        for (step, slot) in rotation.steps(phase).iter().enumerate() {
+            let step_path = format!("{}.{}[{}]", path, name, step);
+            let slot_type = slot_bars
+                .get(slot.slot_bar_index)
+                .and_then(|slot_bar| slot_bar.slots().get(slot.slot_index).copied())
+                .map(|slot| slot.slot_type());
+            match slot_type {
+                None => issues.error(step_path, "no such slot"),
+                Some(SlotType::AttackSkill) => {}
+                Some(slot_type) => issues.warn(
+                    step_path,
+                    format!(
+                        "{:?} is not an attack skill, the step is skipped",
+                        slot_type
+                    ),
+                ),
+            }
+        }
+    }
+}
+
+fn validate_support(config: &SupportConfig, issues: &mut Issues) {
+    let path = "support_config";
+    validate_slot_bars(path, &config.slot_bars(), issues);
+}
+
+fn validate_buff_icons(
+    path: &str,
+    slot_bars: &[SlotBar],
+    buff_icons: &[BuffIcon],
+    issues: &mut Issues,
+) {
+    for (index, buff_icon) in buff_icons.iter().enumerate() {
+        let icon_path = format!("{}.buff_icons[{}]", path, index);
+        let slot = slot_bars
+            .get(buff_icon.slot_bar_index)
+            .and_then(|slot_bar| slot_bar.slots().get(buff_icon.slot_index).copied());
+        match slot {
+            None => issues.error(&icon_path, "no such slot"),
+            Some(slot) if slot.buff_duration().is_none() => issues.warn(
+                &icon_path,
+                "only buffs with a duration are detected, the icon is ignored",
+            ),
+            Some(_) => {}
+        }
+        let icon = &buff_icon.icon;
+        if icon.width == 0 || icon.pixels.len() as u64 != icon.width as u64 * icon.height as u64 {
+            issues.error(
+                format!("{}.icon", icon_path),
+                "pixels don't match the icon size, capture the icon again",
+            );
+        }
+    }
+}
+
+fn validate_slot_bars(path: &str, slot_bars: &[SlotBar], issues: &mut Issues) {
+    let mut used_slots = 0;
+    let mut slots_without_cooldown = 0;
+    for (bar_index, slot_bar) in slot_bars.iter().enumerate() {
+        for (slot_index, slot) in slot_bar.slots().iter().enumerate() {
+            if slot.slot_type == SlotType::Unused || !slot.slot_enabled {
+                continue;
+            }
+            let slot_path = format!("{}.slot_bars[{}].slots[{}]", path, bar_index, slot_index);
+
+            used_slots += 1;
+            if slot.slot_cooldown == Some(0) {
+                slots_without_cooldown += 1;
+                issues.warn(
+                    format!("{}.slot_cooldown", slot_path),
+                    format!(
+                        "{:?} is used on every tick without cooldown",
+                        slot.slot_type
+                    ),
+                );
+            }
+            if slot
+                .slot_threshold
+                .map_or(false, |threshold| threshold > 100)
+            {
+                issues.error(
+                    format!("{}.slot_threshold", slot_path),
+                    "must be a percentage",
+                );
+            }
+        }
+    }
+
+    if used_slots > 1 && slots_without_cooldown == used_slots {
+        issues.error(
+            format!("{}.slot_bars", path),
+            "every slot has a cooldown of 0, set at least one cooldown",
+        );
+    }
+}
+
+fn validate_hud_layout(layout: &HudLayout, issues: &mut Issues) {
+    if layout.width == 0 || layout.height == 0 {
+        issues.error("hud_layout", "capture size must not be empty");
+    }
+    let regions = [
+        ("stat_bars", layout.stat_bars),
+        ("target_hp_bar", layout.target_hp_bar),
+        ("target_mp_bar", layout.target_mp_bar),
+        ("stat_tray", layout.stat_tray),
+        ("action_bar", layout.action_bar),
+        ("buff_area", layout.buff_area),
+    ];
+    for (name, region) in regions {
+        if !is_valid_region(&region, layout) {
+            issues.error(
+                format!("hud_layout.{}", name),
+                "min must not exceed max and the region must fit the capture size",
+            );
+        }
+    }
+    if layout.ignore_area_bottom >= layout.height {
+        issues.error(
+            "hud_layout.ignore_area_bottom",
+            "must be lower than the capture height",
+        );
+    }
+}
+
+fn is_valid_region(region: &HudRegion, layout: &HudLayout) -> bool {
+    region.min_x <= region.max_x
+        && region.min_y <= region.max_y
+        && region.max_x < layout.width
+        && region.max_y < layout.height
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{has_errors, Severity};
+    use crate::ipc::BotConfig;
+
+    fn config(value: serde_json::Value) -> BotConfig {
+        BotConfig::from_value(value).unwrap()
+    }
+
+    fn paths(config: &BotConfig, severity: Severity) -> Vec<String> {
+        config
+            .validate()
+            .into_iter()
+            .filter(|issue| issue.severity == severity)
+            .map(|issue| issue.path)
+            .collect()
+    }
+
+    #[test]
+    fn test_default_is_valid() {
+        assert!(!has_errors(&BotConfig::default().validate()));
+    }
+
+    #[test]
+    fn test_validate() {
+        let slot = |slot_type: &str, cooldown: u32| json!({ "slot_type": slot_type, "slot_cooldown": cooldown, "slot_enabled": true });
+        let unused = json!({ "slot_type": "Unused", "slot_enabled": true });
+        let mut slots = vec![unused; 10];
+        slots[0] = slot("AttackSkill", 0);
+        slots[1] = slot("Food", 0);
+        let mut slot_bars = vec![json!({ "slots": slots.clone() }); 9];
+
+        let mut value = json!({
+            "version": 1,
+            "change_id": 0,
+            "is_running": false,
+            "mode": "Farming",
+            "farming_config": {
+                "slot_bars": slot_bars,
+                "min_mobs_name_width": 50,
+                "max_mobs_name_width": 20,
+                "obstacle_avoidance_max_try": 0,
+                "mobs_name_dilation_x": 100,
+            },
+            "support_config": {},
+            "shout_config": {},
+        });
+        let invalid = config(value.clone());
+        assert_eq!(
+            paths(&invalid, Severity::Error),
+            vec![
+                "farming_config.max_mobs_name_width",
+                "farming_config.mobs_name_dilation_x",
+                "farming_config.slot_bars",
+            ]
+        );
+        let warnings = paths(&invalid, Severity::Warning);
+        assert!(warnings.contains(&"farming_config.obstacle_avoidance_max_try".to_string()));
+        assert!(
+            warnings.contains(&"farming_config.slot_bars[8].slots[1].slot_cooldown".to_string())
+        );
+
+        // One slot with a cooldown is enough
+        slots[1] = slot("Food", 1000);
+        slot_bars = vec![json!({ "slots": slots }); 9];
+        value["farming_config"]["slot_bars"] = json!(slot_bars);
+        value["farming_config"]["max_mobs_name_width"] = json!(80);
+        value["farming_config"]["mobs_name_dilation_x"] = json!(8);
+        assert!(!has_errors(&config(value).validate()));
+    }
+
+    #[test]
+    fn test_validate_support() {
+        let unused = json!({ "slot_type": "Unused", "slot_enabled": true });
+        let mut slots = vec![unused; 10];
+        slots[0] = json!({ "slot_type": "HealSkill", "slot_threshold": 150, "slot_enabled": true });
+        let mut value = json!({
+            "version": 1,
+            "change_id": 0,
+            "is_running": false,
+            "mode": "Farming",
+            "farming_config": {},
+            "support_config": { "slot_bars": vec![json!({ "slots": slots }); 9] },
+            "shout_config": {},
+        });
+        // Only checked while supporting
+        assert!(paths(&config(value.clone()), Severity::Error).is_empty());
+
+        value["mode"] = json!("Support");
+        assert_eq!(
+            paths(&config(value), Severity::Error),
+            (0..9)
+                .map(|bar| format!("support_config.slot_bars[{}].slots[0].slot_threshold", bar))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_new_errors() {
+        let unused = json!({ "slot_type": "Unused", "slot_enabled": true });
+        let mut slots = vec![unused; 10];
+        slots[0] = json!({ "slot_type": "AttackSkill", "slot_cooldown": 0, "slot_enabled": true });
+        slots[1] = json!({ "slot_type": "Food", "slot_cooldown": 0, "slot_enabled": true });
+        let mut value = json!({
+            "version": 1,
+            "change_id": 0,
+            "is_running": false,
+            "mode": "Farming",
+            "farming_config": {
+                "slot_bars": vec![json!({ "slots": slots }); 9],
+                "min_mobs_name_width": 50,
+                "max_mobs_name_width": 20,
+            },
+            "support_config": {},
+            "shout_config": {},
+        });
+        // Loaded from disk with two errors
+        let applied = config(value.clone());
+        assert_eq!(paths(&applied, Severity::Error).len(), 2);
+
+        // Fixing one while the other remains is accepted, as is engaging the bot
+        value["farming_config"]["max_mobs_name_width"] = json!(80);
+        value["is_running"] = json!(true);
+        assert!(config(value.clone()).new_errors(&applied).is_empty());
+
+        // A broken field that was fine is refused
+        value["farming_config"]["min_hp_attack"] = json!(150);
+        assert_eq!(
+            config(value.clone())
+                .new_errors(&applied)
+                .into_iter()
+                .map(|issue| issue.path)
+                .collect::<Vec<_>>(),
+            vec!["farming_config.min_hp_attack"]
+        );
+
+        // Refused changes still carry the running state and mode
+        value["mode"] = json!("Support");
+        let refused = config(value);
+        let state = applied.with_state_of(&refused);
+        assert!(state.is_running());
+        assert_eq!(state.mode(), refused.mode());
+        assert_eq!(state.farming_config().max_mobs_name_width(), 20);
+    }
+}
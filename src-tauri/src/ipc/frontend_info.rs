@@ -1,9 +1,33 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Serialize, Deserialize, Copy, Clone)]
+use super::{SlotBar, SlotType};
+use crate::{
+    data::{Bounds, StatusBarKind, Target},
+    utils::TickStats,
+};
+
+/// How often the bot pressed a slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotUsage {
+    slot_bar_index: usize,
+    slot_index: usize,
+    slot_type: SlotType,
+    uses: u32,
+    max_uses: Option<u32>,
+    /// The stack is assumed empty, the slot is not pressed anymore
+    is_exhausted: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct FrontendInfo {
-    /* enemy_bounds: Option<Vec<Bounds>>,
-    active_enemy_bounds: Option<Bounds>, */
+    /// Detection results of the last iteration, drawn by the debug overlay
+    frame_size: Option<(u32, u32)>,
+    enemy_bounds: Option<Vec<Target>>,
+    active_enemy_bounds: Option<Target>,
+    target_marker_bounds: Option<Bounds>,
+    avoided_bounds: Option<Vec<Bounds>>,
+    stat_bars_bounds: Option<Vec<(StatusBarKind, Bounds)>>,
+
     enemy_kill_count: u32,
     last_fight_duration: u64,
     last_search_duration: u64,
@@ -12,17 +36,40 @@ pub struct FrontendInfo {
     is_attacking: bool,
     is_running: bool,
     is_alive: bool,
+
+    /// Pacing of the main loop, durations in milliseconds
+    last_tick_duration: u64,
+    tick_budget: Option<u64>,
+    missed_deadlines: u64,
+
+    /// Slots used at least once or with a max uses limit
+    slots_usage: Option<Vec<SlotUsage>>,
 }
 
 impl FrontendInfo {
-    /*  pub fn set_enemy_bounds(&mut self, enemy_bounds: Vec<Bounds>) {
-           self.enemy_bounds = Some(enemy_bounds);
-       }
+    pub fn set_frame_size(&mut self, frame_size: (u32, u32)) {
+        self.frame_size = Some(frame_size);
+    }
 
-       pub fn set_active_enemy_bounds(&mut self, active_enemy_bounds: Bounds) {
-           self.active_enemy_bounds = Some(active_enemy_bounds);
-       }
-    */
+    pub fn set_enemy_bounds(&mut self, enemy_bounds: Vec<Target>) {
+        self.enemy_bounds = Some(enemy_bounds);
+    }
+
+    pub fn set_active_enemy_bounds(&mut self, active_enemy_bounds: Option<Target>) {
+        self.active_enemy_bounds = active_enemy_bounds;
+    }
+
+    pub fn set_target_marker_bounds(&mut self, target_marker_bounds: Option<Bounds>) {
+        self.target_marker_bounds = target_marker_bounds;
+    }
+
+    pub fn set_avoided_bounds(&mut self, avoided_bounds: Vec<Bounds>) {
+        self.avoided_bounds = Some(avoided_bounds);
+    }
+
+    pub fn set_stat_bars_bounds(&mut self, stat_bars_bounds: Vec<(StatusBarKind, Bounds)>) {
+        self.stat_bars_bounds = Some(stat_bars_bounds);
+    }
 
     pub fn set_kill_count(&mut self, enemy_kill_count: u32) {
         self.enemy_kill_count = enemy_kill_count;
@@ -36,6 +83,35 @@ impl FrontendInfo {
         self.last_search_duration = action_duration.0.try_into().unwrap_or(0);
         self.last_fight_duration = action_duration.1.try_into().unwrap_or(0);
     }
+
+    pub fn set_tick_stats(&mut self, stats: TickStats) {
+        self.last_tick_duration = stats.duration.as_millis().try_into().unwrap_or(u64::MAX);
+        self.tick_budget = stats
+            .budget
+            .map(|budget| budget.as_millis().try_into().unwrap_or(u64::MAX));
+        self.missed_deadlines = stats.missed_deadlines;
+    }
+
+    pub fn set_slots_usage(&mut self, slot_bars: &[SlotBar], slots_usage_count: [[u32; 10]; 9]) {
+        let mut slots_usage = vec![];
+        for (slot_bar_index, slot_bar) in slot_bars.iter().enumerate() {
+            for (slot_index, slot) in slot_bar.slots().iter().enumerate() {
+                let uses = slots_usage_count[slot_bar_index][slot_index];
+                if uses > 0 || slot.max_uses().is_some() {
+                    slots_usage.push(SlotUsage {
+                        slot_bar_index,
+                        slot_index,
+                        slot_type: slot.slot_type(),
+                        uses,
+                        max_uses: slot.max_uses(),
+                        is_exhausted: slot.is_exhausted(uses),
+                    });
+                }
+            }
+        }
+        self.slots_usage = Some(slots_usage);
+    }
+
     pub fn set_is_attacking(&mut self, is_attacking: bool) {
         self.is_attacking = is_attacking;
     }
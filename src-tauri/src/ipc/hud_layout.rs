@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+/// Rectangle of the client area, both bounds included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HudRegion {
+    pub min_x: u32,
+    pub min_y: u32,
+    pub max_x: u32,
+    pub max_y: u32,
+}
+
+impl HudRegion {
+    pub const fn new(min_x: u32, min_y: u32, max_x: u32, max_y: u32) -> Self {
+        Self {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+
+    fn scaled(&self, scale_x: f32, scale_y: f32) -> Self {
+        Self {
+            min_x: scale(self.min_x, scale_x),
+            min_y: scale(self.min_y, scale_y),
+            max_x: scale(self.max_x, scale_x),
+            max_y: scale(self.max_y, scale_y),
+        }
+    }
+}
+
+fn scale(value: u32, factor: f32) -> u32 {
+    (value as f32 * factor).round() as u32
+}
+
+/// Position of the client HUD elements, measured on a `width` x `height` capture.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HudLayout {
+    /// Capture size the regions were measured on
+    pub width: u32,
+    pub height: u32,
+
+    /// Player HP, MP and FP bars
+    pub stat_bars: HudRegion,
+    pub target_hp_bar: HudRegion,
+    pub target_mp_bar: HudRegion,
+
+    /// Player stats window, skipped when looking for mobs
+    pub stat_tray: HudRegion,
+
+    /// Slots of the visible slot bar, split into 10 slots of equal width
+    #[serde(default = "default_action_bar")]
+    pub action_bar: HudRegion,
+
+    /// Icons of the active buffs, below the player stats window
+    #[serde(default = "default_buff_area")]
+    pub buff_area: HudRegion,
+
+    /// Height of the bottom area ignored when looking for mobs (avoids clicks outside the window)
+    pub ignore_area_bottom: u32,
+}
+
+impl Default for HudLayout {
+    /// Layout of the 800x600 client window the bot forces while farming.
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            stat_bars: HudRegion::new(105, 30, 225, 110),
+            target_hp_bar: HudRegion::new(300, 30, 550, 60),
+            target_mp_bar: HudRegion::new(300, 50, 550, 60),
+            stat_tray: HudRegion::new(0, 0, 250, 110),
+            action_bar: default_action_bar(),
+            buff_area: default_buff_area(),
+            ignore_area_bottom: 110,
+        }
+    }
+}
+
+/// Layouts saved before the action bar was added get the default one
+fn default_action_bar() -> HudRegion {
+    HudRegion::new(240, 556, 559, 587)
+}
+
+/// Layouts saved before the buff area was added get the default one
+fn default_buff_area() -> HudRegion {
+    HudRegion::new(0, 112, 399, 171)
+}
+
+impl HudLayout {
+    /// Get the layout scaled to a capture of the given size.
+    pub fn scaled_to(&self, width: u32, height: u32) -> Self {
+        if (width, height) == (self.width, self.height) || self.width == 0 || self.height == 0 {
+            return self.clone();
+        }
+
+        let scale_x = width as f32 / self.width as f32;
+        let scale_y = height as f32 / self.height as f32;
+        Self {
+            width,
+            height,
+            stat_bars: self.stat_bars.scaled(scale_x, scale_y),
+            target_hp_bar: self.target_hp_bar.scaled(scale_x, scale_y),
+            target_mp_bar: self.target_mp_bar.scaled(scale_x, scale_y),
+            stat_tray: self.stat_tray.scaled(scale_x, scale_y),
+            action_bar: self.action_bar.scaled(scale_x, scale_y),
+            buff_area: self.buff_area.scaled(scale_x, scale_y),
+            ignore_area_bottom: scale(self.ignore_area_bottom, scale_y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HudLayout, HudRegion};
+
+    #[test]
+    fn test_scaled_to() {
+        let layout = HudLayout::default();
+        assert_eq!(layout.scaled_to(800, 600), layout);
+
+        let scaled = layout.scaled_to(1600, 900);
+        assert_eq!(scaled.stat_bars, HudRegion::new(210, 45, 450, 165));
+        assert_eq!(scaled.target_mp_bar, HudRegion::new(600, 75, 1100, 90));
+        assert_eq!(scaled.action_bar, HudRegion::new(480, 834, 1118, 881));
+        assert_eq!(scaled.buff_area, HudRegion::new(0, 168, 798, 257));
+        assert_eq!(scaled.ignore_area_bottom, 165);
+        assert_eq!(scaled.scaled_to(800, 600), layout);
+    }
+}
@@ -0,0 +1,12 @@
+//! Bot logic shared by the tauri `app` and the headless `neuz-cli`.
+
+pub mod behavior;
+pub mod data;
+pub mod image_analyzer;
+pub mod ipc;
+pub mod movement;
+pub mod platform;
+pub mod profile_storage;
+pub mod recorder;
+pub mod session;
+pub mod utils;
@@ -3,39 +3,39 @@
     windows_subsystem = "windows"
 )]
 
-mod behavior;
-mod data;
-mod image_analyzer;
-mod ipc;
-mod movement;
-mod platform;
-mod utils;
-
 use std::{
-    fs::{self},
-    io,
     path::{Path, PathBuf},
-    sync::Arc,
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use app::{
+    behavior::{Behavior, FarmingBehavior, ShoutBehavior, SupportBehavior},
+    data::Point,
+    image_analyzer::{CalibrationTarget, IconTemplate, ImageAnalyzer},
+    ipc::{BotConfig, BotMode, FarmingConfig, FrontendInfo},
+    movement::MovementAccessor,
+    platform::{InputSink, KeyMode, RecordingInputSink},
+    profile_storage::ProfileStorage,
+    recorder::Recorder,
+    session::{SessionRecord, SessionTracker},
+    utils::{metrics, Clock, SystemClock, TickScheduler},
+};
 use guard::guard;
-use ipc::FrontendInfo;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use slog::{Drain, Level, Logger};
 use tauri::{LogicalSize, Manager, Size, Window};
 
-use crate::{
-    behavior::{Behavior, FarmingBehavior, ShoutBehavior, SupportBehavior},
-    image_analyzer::ImageAnalyzer,
-    ipc::{BotConfig, BotMode},
-    movement::MovementAccessor,
-    platform::{eval_send_key, KeyMode},
-    utils::Timer,
-};
+/// How often timing metrics are sent to the frontend
+const METRICS_INTERVAL: Duration = Duration::from_secs(5);
 
 struct AppState {
     logger: Logger,
+    /// Frame the calibration wizard is working on
+    calibration_frame: Mutex<Option<ImageAnalyzer>>,
 }
 
 fn main() {
@@ -72,7 +72,10 @@ fn main() {
     // Build app
     tauri::Builder::default()
         // .menu(tauri::Menu::os_default(&context.package_info().name))
-        .manage(AppState { logger })
+        .manage(AppState {
+            logger,
+            calibration_frame: Mutex::new(None),
+        })
         .invoke_handler(tauri::generate_handler![
             start_bot,
             create_window,
@@ -82,8 +85,14 @@ fn main() {
             rename_profile,
             copy_profile,
             reset_profile,
+            export_profile,
+            import_profile,
+            session_history,
             focus_client,
             toggle_main_size,
+            capture_calibration_frame,
+            calibrate,
+            capture_buff_icon,
         ])
         .run(context)
         .expect("error while running tauri application");
@@ -124,6 +133,63 @@ fn toggle_main_size(
     }
 }
 
+/// Capture the client window for the calibration wizard and return it as PNG.
+#[tauri::command]
+fn capture_calibration_frame(
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<u8>, String> {
+    let window = app_handle
+        .get_window("client")
+        .ok_or("Client window is not open")?;
+    let mut image_analyzer = ImageAnalyzer::new(&window);
+    image_analyzer.capture_window(&state.logger, &FarmingConfig::default());
+    let png = image_analyzer
+        .encode_png()
+        .ok_or("Client window could not be captured")?;
+
+    *state.calibration_frame.lock() = Some(image_analyzer);
+    Ok(png)
+}
+
+/// Derive reference colors from the element at `x`, `y` of the calibration frame into `config`.
+#[tauri::command]
+fn calibrate(
+    target: CalibrationTarget,
+    x: u32,
+    y: u32,
+    mut config: FarmingConfig,
+    state: tauri::State<AppState>,
+) -> Result<FarmingConfig, String> {
+    let calibration_frame = state.calibration_frame.lock();
+    let calibration = calibration_frame
+        .as_ref()
+        .ok_or("No calibration frame was captured")?
+        .calibrate(target, Point::new(x, y))
+        .ok_or("No matching colors found at this position")?;
+    slog::info!(state.logger, "Calibrated colors"; "target" => format!("{:?}", target), "colors" => format!("{:?}", calibration.colors), "tolerance" => calibration.tolerance);
+
+    config.apply_calibration(&calibration);
+    Ok(config)
+}
+
+/// Cut the buff icon at `x`, `y` of the calibration frame, the frontend assigns it to a slot.
+#[tauri::command]
+fn capture_buff_icon(
+    x: u32,
+    y: u32,
+    state: tauri::State<AppState>,
+) -> Result<IconTemplate, String> {
+    let calibration_frame = state.calibration_frame.lock();
+    let icon = calibration_frame
+        .as_ref()
+        .ok_or("No calibration frame was captured")?
+        .icon_template(Point::new(x, y))
+        .ok_or("The icon must not touch the border of the capture")?;
+    slog::info!(state.logger, "Captured buff icon"; "x" => x, "y" => y);
+    Ok(icon)
+}
+
 #[tauri::command]
 fn focus_client(_state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
     let window = app_handle.get_window("client");
@@ -132,102 +198,29 @@ fn focus_client(_state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
 }
 
 #[tauri::command]
-fn get_profiles(_state: tauri::State<AppState>, app_handle: tauri::AppHandle) -> Vec<String> {
-    drop(fs::create_dir(
-        format!(
-            r"{}\",
-            app_handle
-                .path_resolver()
-                .app_data_dir()
-                .unwrap()
-                .to_string_lossy()
-        )
-        .clone(),
-    ));
-    let paths = fs::read_dir(format!(
-        r"{}\",
-        app_handle
-            .path_resolver()
-            .app_data_dir()
-            .unwrap()
-            .to_string_lossy()
-    ))
-    .unwrap();
-    let mut profiles = vec![];
-
-    for entry in paths.flatten() {
-        if entry.file_name().to_str().unwrap().starts_with("profile_") {
-            profiles.push(String::from(entry.file_name().to_str().unwrap()));
-        }
-    }
-    if profiles.is_empty() {
-        drop(fs::create_dir(
-            format!(
-                r"{}\profile_DEFAULT",
-                app_handle
-                    .path_resolver()
-                    .app_data_dir()
-                    .unwrap()
-                    .to_string_lossy()
-            )
-            .clone(),
-        ));
-        profiles.push("profile_DEFAULT".to_string());
-    }
-
-    profiles
+fn get_profiles(
+    _state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    profile_storage(&app_handle)?
+        .list()
+        .map_err(|err| err.to_string())
 }
 
-fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
-    fs::create_dir_all(&dst)?;
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        if ty.is_dir() {
-            copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        } else {
-            fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
-        }
-    }
-    Ok(())
-}
-fn config_folder_path(app_handle: &tauri::AppHandle, profile_id: &String) -> String {
-    format!(
-        r"{}\profile_{}",
-        app_handle
-            .path_resolver()
-            .app_data_dir()
-            .unwrap()
-            .to_string_lossy(),
-        profile_id
-    )
-}
-fn config_file_path(app_handle: &tauri::AppHandle, profile_id: &String) -> String {
-    format!(
-        r"{}\.botconfig_{}",
-        app_handle
-            .path_resolver()
-            .app_data_dir()
-            .unwrap()
-            .to_string_lossy(),
-        profile_id
-    )
+fn profile_storage(app_handle: &tauri::AppHandle) -> Result<ProfileStorage, String> {
+    ProfileStorage::from_app(app_handle).map_err(|err| err.to_string())
 }
+
 #[tauri::command]
 fn copy_profile(
     profile_id: String,
     new_profile_id: String,
     _state: tauri::State<AppState>,
     app_handle: tauri::AppHandle,
-) {
-    drop(fs::copy(
-        config_file_path(&app_handle, &profile_id),
-        config_file_path(&app_handle, &new_profile_id).clone(),
-    ));
-    drop(copy_dir_all(
-        config_folder_path(&app_handle, &profile_id),
-        config_folder_path(&app_handle, &new_profile_id),
-    ));
+) -> Result<(), String> {
+    profile_storage(&app_handle)?
+        .copy(&profile_id, &new_profile_id)
+        .map_err(|err| err.to_string())
 }
 
 #[tauri::command]
@@ -235,8 +228,10 @@ fn create_profile(
     profile_id: String,
     _state: tauri::State<AppState>,
     app_handle: tauri::AppHandle,
-) {
-    drop(fs::create_dir(config_folder_path(&app_handle, &profile_id)));
+) -> Result<(), String> {
+    profile_storage(&app_handle)?
+        .create(&profile_id)
+        .map_err(|err| err.to_string())
 }
 
 #[tauri::command]
@@ -244,12 +239,10 @@ fn remove_profile(
     profile_id: String,
     _state: tauri::State<AppState>,
     app_handle: tauri::AppHandle,
-) {
-    drop(fs::remove_dir_all(config_folder_path(
-        &app_handle,
-        &profile_id,
-    )));
-    drop(fs::remove_file(config_file_path(&app_handle, &profile_id)));
+) -> Result<(), String> {
+    profile_storage(&app_handle)?
+        .remove(&profile_id)
+        .map_err(|err| err.to_string())
 }
 
 #[tauri::command]
@@ -258,59 +251,147 @@ fn rename_profile(
     new_profile_id: String,
     _state: tauri::State<AppState>,
     app_handle: tauri::AppHandle,
-) {
-    drop(fs::rename(
-        config_folder_path(&app_handle, &profile_id),
-        config_folder_path(&app_handle, &new_profile_id),
-    ));
-    drop(fs::rename(
-        config_folder_path(&app_handle, &profile_id),
-        config_folder_path(&app_handle, &new_profile_id).clone(),
-    ));
+) -> Result<(), String> {
+    profile_storage(&app_handle)?
+        .rename(&profile_id, &new_profile_id)
+        .map_err(|err| err.to_string())
 }
 
 #[tauri::command]
-fn reset_profile(profile_id: String, _state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
-    drop(fs::remove_dir_all(config_folder_path(
-        &app_handle,
-        &profile_id,
-    )));
-    drop(fs::remove_file(
-        config_file_path(&app_handle, &profile_id).clone(),
-    ));
-    drop(fs::create_dir(
-        config_folder_path(&app_handle, &profile_id).clone(),
-    ));
+fn reset_profile(
+    profile_id: String,
+    _state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    profile_storage(&app_handle)?
+        .reset(&profile_id)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+fn export_profile(
+    profile_id: String,
+    path: PathBuf,
+    include_session_data: bool,
+    _state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
+    profile_storage(&app_handle)?
+        .export(&profile_id, &path, include_session_data)
+        .map_err(|err| err.to_string())
+}
+
+/// Returns the id of the imported profile.
+#[tauri::command]
+fn import_profile(
+    path: PathBuf,
+    profile_id: Option<String>,
+    _state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    profile_storage(&app_handle)?
+        .import(&path, profile_id.as_deref())
+        .map_err(|err| err.to_string())
+}
+
+/// Statistics of the finished bot sessions of a profile, oldest first.
+#[tauri::command]
+fn session_history(
+    profile_id: String,
+    _state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<SessionRecord>, String> {
+    profile_storage(&app_handle)?
+        .session_history(&profile_id)
+        .map_err(|err| err.to_string())
 }
 
 #[tauri::command]
-async fn create_window(profile_id: String, app_handle: tauri::AppHandle) {
+async fn create_window(profile_id: String, app_handle: tauri::AppHandle) -> Result<(), String> {
+    let data_directory = profile_storage(&app_handle)?
+        .profile_dir(&profile_id)
+        .map_err(|err| err.to_string())?;
     let window = tauri::WindowBuilder::new(
         &app_handle,
         "client",
         tauri::WindowUrl::External("https://universe.flyff.com/play".parse().unwrap()),
     )
-    .data_directory(PathBuf::from(format!(
-        r"{}\profile_{}",
-        app_handle
-            .path_resolver()
-            .app_data_dir()
-            .unwrap()
-            .to_string_lossy(),
-        profile_id
-    )))
+    .data_directory(data_directory)
     //.resizable(false)
     .center()
     .inner_size(800.0, 600.0)
     .title(format!("{} | Flyff Universe", profile_id))
     .build()
-    .unwrap();
+    .map_err(|err| err.to_string())?;
     drop(window.show());
 
     let main_window = app_handle.get_window("main").unwrap();
     drop(main_window.set_title(format!("{} Neuz | MadrigalStreetCartel", profile_id).as_str()));
     //window.once_global("tauri://close-requested", move |_| app_handle.restart());
+    Ok(())
 }
+
+fn save_recording(recorder: &mut Recorder, logger: &Logger, reason: &str) {
+    match recorder.dump(reason) {
+        Ok(Some(path)) => {
+            slog::info!(logger, "Recording saved"; "reason" => reason, "path" => path.to_string_lossy().to_string())
+        }
+        Ok(None) => {}
+        Err(err) => {
+            slog::error!(logger, "Failed to save recording"; "reason" => reason, "error" => err.to_string())
+        }
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or_default()
+}
+
+/// Append the statistics of the running session, if any, to the profile history and keep its
+/// timing metrics next to the recordings.
+fn save_session(
+    session: &mut Option<SessionTracker>,
+    sessions_path: &Path,
+    session_dir: &Path,
+    clock: &dyn Clock,
+    logger: &Logger,
+) {
+    guard!(let Some(session) = session.take() else { return; });
+    let record = session.finish(unix_time(), clock.now());
+    match record.append_to(sessions_path) {
+        Ok(()) => {
+            slog::info!(logger, "Session saved"; "kills" => record.kills, "deaths" => record.deaths)
+        }
+        Err(err) => {
+            slog::error!(logger, "Failed to save session"; "error" => err.to_string())
+        }
+    }
+
+    save_metrics(
+        &session_dir.join(format!("metrics_{}.json", record.started_at)),
+        logger,
+    );
+    // The next session starts with fresh timings
+    metrics::global().reset();
+}
+
+fn save_metrics(path: &Path, logger: &Logger) {
+    if metrics::global().summaries().is_empty() {
+        return;
+    }
+    match metrics::global().dump(path) {
+        Ok(()) => {
+            slog::info!(logger, "Metrics saved"; "path" => path.to_string_lossy().to_string())
+        }
+        Err(err) => {
+            slog::error!(logger, "Failed to save metrics"; "error" => err.to_string())
+        }
+    }
+}
+
 fn should_disconnect(config: &BotConfig) -> bool {
     return match config.mode().unwrap() {
         BotMode::Farming => config.farming_config().on_death_disconnect(),
@@ -319,36 +400,71 @@ fn should_disconnect(config: &BotConfig) -> bool {
     };
 }
 #[tauri::command]
-fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
+fn start_bot(
+    profile_id: String,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), String> {
     let logger = state.logger.clone();
-    let config_path = format!(
-        r"{}\.botconfig_{}",
-        app_handle
-            .path_resolver()
-            .app_data_dir()
-            .unwrap()
-            .to_string_lossy(),
-        profile_id
-    )
-    .clone();
+    let storage = profile_storage(&app_handle)?;
+    let config_path = storage
+        .config_path(&profile_id)
+        .map_err(|err| err.to_string())?;
+    let profile_dir = storage
+        .profile_dir(&profile_id)
+        .map_err(|err| err.to_string())?;
+    let sessions_path = storage
+        .sessions_path(&profile_id)
+        .map_err(|err| err.to_string())?;
 
     std::thread::spawn(move || {
         let logger = logger.clone();
 
+        // Never silently drop a config that could not be read, keep a copy and tell the user
+        let (initial_config, config_error) = match BotConfig::load(&config_path) {
+            Ok(config) => (config, None),
+            Err(err) => {
+                slog::error!(logger, "Failed to load config"; "error" => err.to_string(), "path" => config_path.to_string_lossy().to_string());
+                let message = match BotConfig::backup(&config_path) {
+                    Ok(backup_path) => format!(
+                        "Your config could not be loaded ({}), default settings are used instead. The previous config was saved to {}",
+                        err,
+                        backup_path.display()
+                    ),
+                    Err(backup_err) => format!(
+                        "Your config could not be loaded ({}), default settings are used instead. The previous config could not be backed up ({})",
+                        err, backup_err
+                    ),
+                };
+                (BotConfig::default(), Some(message))
+            }
+        };
+
         let mut last_config_change_id = 0;
-        let config: Arc<RwLock<BotConfig>> =
-            Arc::new(RwLock::new(BotConfig::deserialize_or_default(config_path)));
+        let config: Arc<RwLock<BotConfig>> = Arc::new(RwLock::new(initial_config));
 
         // Listen for config changes from the UI
         let local_config = config.clone();
         let logger_botconfig_c2s = logger.clone();
+        let app_handle_c2s = app_handle.clone();
 
         app_handle.listen_global("bot_config_c2s", move |e| {
             slog::trace!(logger_botconfig_c2s, "Received config change"; "event_payload" => e.payload());
             if let Some(payload) = e.payload() {
                 match serde_json::from_str::<BotConfig>(payload) {
                     Ok(new_config) => {
-                        *local_config.write() = new_config.changed();
+                        drop(app_handle_c2s.emit_all("bot_config_validation", &new_config.validate()));
+
+                        // Changes adding errors are refused, only the running state and mode are
+                        // taken. The applied config is sent back to the frontend.
+                        let mut config = local_config.write();
+                        let new_errors = new_config.new_errors(&config);
+                        if new_errors.is_empty() {
+                            *config = new_config.changed();
+                        } else {
+                            slog::warn!(logger_botconfig_c2s, "Refused invalid config change"; "issues" => format!("{:?}", new_errors));
+                            *config = config.clone().with_state_of(&new_config).changed();
+                        }
                     }
                     Err(e) => {
                         slog::error!(logger_botconfig_c2s, "Failed to parse config change"; "error" => e.to_string(), "error_payload" => payload);
@@ -363,6 +479,13 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
             local_config.write().toggle_active();
         });
 
+        // Listen for refilled slots
+        let reset_slots_usage = Arc::new(AtomicBool::new(false));
+        let local_reset_slots_usage = reset_slots_usage.clone();
+        app_handle.listen_global("reset_slots_usage", move |_| {
+            local_reset_slots_usage.store(true, Ordering::Relaxed);
+        });
+
         let send_config = |config: &BotConfig| {
             drop(app_handle.emit_all("bot_config_s2c", config) as Result<(), _>)
         };
@@ -376,18 +499,36 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
 
         // Send initial config to frontend
         send_config(&config.read());
+        if let Some(message) = config_error {
+            drop(app_handle.emit_all("bot_config_error", message));
+        }
+        drop(app_handle.emit_all("bot_config_validation", config.read().validate()));
 
         let window = app_handle.get_window("client").unwrap();
         let mut image_analyzer: ImageAnalyzer = ImageAnalyzer::new(&window);
-        image_analyzer.window_id = platform::get_window_id(&window).unwrap_or(0);
 
-        // Create movement accessor
-        let movement = MovementAccessor::new(window.clone() /*&accessor*/);
+        // Create movement accessor, inputs are kept around for the recorder
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let input = Arc::new(RecordingInputSink::forwarding(Arc::new(window.clone())));
+        let movement = MovementAccessor::new(input.clone(), clock.clone());
 
         // Instantiate behaviors
-        let mut farming_behavior = FarmingBehavior::new(&logger, &movement, &window);
-        let mut shout_behavior = ShoutBehavior::new(&logger, &movement, &window);
-        let mut support_behavior = SupportBehavior::new(&logger, &movement, &window);
+        let mut farming_behavior = FarmingBehavior::new(&logger, &movement, &*input, &*clock);
+        let mut shout_behavior = ShoutBehavior::new(&logger, &movement, &*input, &*clock);
+        let mut support_behavior = SupportBehavior::new(&logger, &movement, &*input, &*clock);
+
+        let session_dir = profile_dir
+            .join("recordings")
+            .join(format!("session_{}", unix_time()));
+        let mut recorder = Recorder::new(session_dir.clone(), clock.clone());
+        let mut scheduler = TickScheduler::new(clock.clone());
+
+        // Timings of previous sessions are not relevant anymore
+        metrics::global().reset();
+        let mut last_metrics_update = clock.now();
+
+        // Statistics of the current engagement, saved to the history once it ends
+        let mut session: Option<SessionTracker> = None;
 
         let mut last_mode: Option<BotMode> = None;
         let mut frontend_info: Arc<RwLock<FrontendInfo>> =
@@ -395,24 +536,14 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
         send_info(&frontend_info.read());
         // Enter main loop
         loop {
-            let timer = Timer::start_new("main_loop");
             let config = &*config.read();
-            let mut frontend_info_mut = *frontend_info.read();
+            scheduler.set_tick_rate(config.tick_rate());
+            let timer = scheduler.start_tick("main_loop");
+            let mut frontend_info_mut = frontend_info.read().clone();
 
             // Send changed config to frontend if needed
             if config.change_id() > last_config_change_id {
-                config.serialize(
-                    format!(
-                        r"{}\.botconfig_{}",
-                        app_handle
-                            .path_resolver()
-                            .app_data_dir()
-                            .unwrap()
-                            .to_string_lossy(),
-                        profile_id
-                    )
-                    .clone(),
-                );
+                config.serialize(&config_path);
                 send_config(config);
                 last_config_change_id = config.change_id();
 
@@ -422,19 +553,27 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
                 support_behavior.update(config);
             }
 
+            if reset_slots_usage.swap(false, Ordering::Relaxed) {
+                farming_behavior.reset_slots_usage();
+                support_behavior.reset_slots_usage();
+            }
+
             // Client window is closed
             if window.is_resizable().is_err() {
+                save_session(&mut session, &sessions_path, &session_dir, &*clock, &logger);
                 app_handle.restart();
                 break;
             }
 
             // Continue early if the bot is not engaged
             if !config.is_running() {
+                save_session(&mut session, &sessions_path, &session_dir, &*clock, &logger);
                 if !window.is_resizable().unwrap() {
                     drop(window.set_resizable(true));
                 }
                 std::thread::sleep(std::time::Duration::from_millis(250));
                 timer.silence();
+                scheduler.skip_tick();
                 continue;
             }
 
@@ -454,9 +593,25 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
             guard!(let Some(mode) = config.mode() else {
                 std::thread::sleep(std::time::Duration::from_millis(100));
                 timer.silence();
+                scheduler.skip_tick();
                 continue;
             });
 
+            // A mode change starts a new session
+            if session
+                .as_ref()
+                .map_or(false, |session| session.mode() != &mode)
+            {
+                save_session(&mut session, &sessions_path, &session_dir, &*clock, &logger);
+            }
+            if session.is_none() {
+                session = Some(SessionTracker::new(
+                    mode.clone(),
+                    unix_time(),
+                    farming_behavior.kill_count(),
+                ));
+            }
+
             // Check if mode is different from last mode
             if let Some(last_mode) = last_mode.as_ref() {
                 if &mode != last_mode {
@@ -476,7 +631,11 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
                 }
             }
 
+            recorder.set_capacity(config.recorder_frames());
+
             // Capture client window
+            image_analyzer.set_hud_layout(config.hud_layout());
+            image_analyzer.set_status_bar_colors(config.farming_config().status_bar_colors());
             image_analyzer.capture_window(&logger, config.farming_config());
 
             // Try capturing the window contents
@@ -485,35 +644,77 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
                 image_analyzer
                     .client_stats
                     .update(&image_analyzer.clone(), &logger);
+                if let Some(frame_size) = image_analyzer.image_size() {
+                    frontend_info_mut.set_frame_size(frame_size);
+                }
+                frontend_info_mut.set_stat_bars_bounds(image_analyzer.client_stats.bars_bounds());
 
                 // Run the current behavior
-                guard!(let Some(mode) = config.mode() else { continue; });
+                guard!(let Some(mode) = config.mode() else {
+                    timer.silence();
+                    scheduler.skip_tick();
+                    continue;
+                });
 
                 // Stop bot in case of death
                 let is_alive = image_analyzer.client_stats.is_alive();
 
                 if !is_alive {
+                    let mut revive_wait = None;
+                    if let Some(session) = session.as_mut() {
+                        session.enter_state("Dead", clock.now());
+                    }
                     if frontend_info_mut.is_alive() {
+                        recorder.record(
+                            image_analyzer.image(),
+                            "Dead".to_string(),
+                            &image_analyzer.client_stats,
+                            input.take(),
+                        );
+                        save_recording(&mut recorder, &logger, "death");
+                        if let Some(session) = session.as_mut() {
+                            session.record_death();
+                        }
+
                         let should_disconnect = should_disconnect(config);
                         if should_disconnect {
+                            save_session(
+                                &mut session,
+                                &sessions_path,
+                                &session_dir,
+                                &*clock,
+                                &logger,
+                            );
                             app_handle.exit(0);
                             return;
                         }
 
                         frontend_info_mut.set_is_alive(false);
+                        frontend_info_mut.set_tick_stats(scheduler.stats());
                         frontend_info = Arc::new(RwLock::new(frontend_info_mut));
                         // Send infos to frontend
                         send_info(&frontend_info.read());
                     } else {
-                        eval_send_key(&window, "Enter", KeyMode::Press);
-                        std::thread::sleep(Duration::from_millis(500));
+                        input.send_key("Enter", KeyMode::Press);
+                        revive_wait = Some(Duration::from_millis(500));
+                    }
+
+                    // Dead ticks are paced like the others, waiting for the revive is not part
+                    // of the tick
+                    drop(timer);
+                    scheduler.finish_tick();
+                    if let Some(revive_wait) = revive_wait {
+                        std::thread::sleep(revive_wait);
                     }
                     continue;
                 } else if is_alive && !frontend_info_mut.is_alive() {
                     frontend_info_mut.set_is_alive(true);
+                    // Buffs are lost on death
+                    farming_behavior.reset_buff_timers();
+                    support_behavior.reset_buff_timers();
                     let should_disconnect = should_disconnect(config);
                     if !should_disconnect {
-                        eval_send_key(&window, "Escape", KeyMode::Press);
+                        input.send_key("Escape", KeyMode::Press);
                     }
                 }
                 match mode {
@@ -539,13 +740,60 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
                         );
                     }
                 }
+
+                if let Some(session) = session.as_mut() {
+                    for (slot_bar_index, slot_index) in input.take_slots() {
+                        if let Some(slot_type) = config.slot_type(&mode, slot_bar_index, slot_index)
+                        {
+                            session.record_slot_use(slot_type);
+                        }
+                    }
+                    session.set_kill_count(farming_behavior.kill_count());
+                    match mode {
+                        BotMode::Farming => {
+                            session.enter_state(farming_behavior.state_kind(), clock.now())
+                        }
+                        _ => session.enter_state(&mode.to_string(), clock.now()),
+                    }
+                }
+
+                // Keep the tick for post-mortem analysis
+                let state = match mode {
+                    BotMode::Farming => farming_behavior.state_name(),
+                    _ => mode.to_string(),
+                };
+                recorder.record(
+                    image_analyzer.image(),
+                    state,
+                    &image_analyzer.client_stats,
+                    input.take(),
+                );
+                if farming_behavior.is_mobs_timeout_reached() {
+                    save_recording(&mut recorder, &logger, "mobs_timeout");
+                    save_session(&mut session, &sessions_path, &session_dir, &*clock, &logger);
+                    app_handle.exit(0);
+                    return;
+                }
+
+                frontend_info_mut.set_tick_stats(scheduler.stats());
                 frontend_info = Arc::new(RwLock::new(frontend_info_mut));
                 // Send infos to frontend
                 send_info(&frontend_info.read());
+
+                if clock.elapsed(last_metrics_update) >= METRICS_INTERVAL {
+                    drop(app_handle.emit_all("bot_metrics_s2c", metrics::global().summaries()));
+                    last_metrics_update = clock.now();
+                }
             }
 
             // Update last mode
             last_mode = config.mode();
+
+            // Wait for the next tick, the timer reports overruns
+            drop(timer);
+            scheduler.finish_tick();
         }
     });
+
+    Ok(())
 }
@@ -1,20 +1,27 @@
+use std::sync::Arc;
+
 use parking_lot::Mutex;
-use tauri::Window;
 
 //use crate::platform::PlatformAccessor;
 use super::MovementCoordinator;
+use crate::{platform::InputSink, utils::Clock};
 
 pub struct MovementAccessor {
     coordinator: Mutex<MovementCoordinator>,
 }
 
 impl MovementAccessor {
-    pub fn new(window: Window /*platform: &'a PlatformAccessor<'a>*/) -> Self {
+    pub fn new(input: Arc<dyn InputSink>, clock: Arc<dyn Clock>) -> Self {
         Self {
-            coordinator: Mutex::new(MovementCoordinator::new(window /*platform*/)),
+            coordinator: Mutex::new(MovementCoordinator::new(input, clock /*platform*/)),
         }
     }
 
+    /// Make random durations and directions reproducible, e.g. to replay recorded frames.
+    pub fn set_rng_seed(&self, seed: u64) {
+        self.coordinator.lock().set_rng_seed(seed);
+    }
+
     pub fn schedule<F>(&self, func: F)
     where
         F: Fn(&mut MovementCoordinator),
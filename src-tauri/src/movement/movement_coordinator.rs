@@ -1,9 +1,11 @@
-use std::{ops::Range, thread, time::Duration};
+use std::{ops::Range, sync::Arc, time::Duration};
 
-use rand::Rng;
-use tauri::Window;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use crate::platform::{eval_send_key, eval_send_message /* , PlatformAccessor*/, KeyMode};
+use crate::{
+    platform::{InputSink /* , PlatformAccessor*/, KeyMode},
+    utils::Clock,
+};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
@@ -29,7 +31,7 @@ pub enum ActionDuration {
 }
 
 impl ActionDuration {
-    fn to_duration(&self, rng: &mut rand::rngs::ThreadRng) -> Duration {
+    fn to_duration(&self, rng: &mut StdRng) -> Duration {
         match self {
             Self::Fixed(ms) => Duration::from_millis(*ms),
             Self::Random(range) => Duration::from_millis(rng.gen_range(range.clone())),
@@ -55,20 +57,27 @@ pub enum Movement<'a> {
 }
 
 pub struct MovementCoordinator {
-    rng: rand::rngs::ThreadRng,
-    window: Window,
+    rng: StdRng,
+    input: Arc<dyn InputSink>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<'a> MovementCoordinator {
-    pub fn new(window: Window) -> Self {
-        let rng = rand::thread_rng();
+    pub fn new(input: Arc<dyn InputSink>, clock: Arc<dyn Clock>) -> Self {
+        let rng = StdRng::from_entropy();
 
         Self {
             rng, /*, platform */
-            window,
+            input,
+            clock,
         }
     }
 
+    /// Make random durations and directions reproducible, e.g. to replay recorded frames.
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     // Wrapper functions
 
     /*   pub fn with_probability<F>(&mut self, probability: f64, func: F)
@@ -94,9 +103,9 @@ impl<'a> MovementCoordinator {
     fn play_single(&mut self, movement: Movement) {
         match movement {
             Movement::Jump => {
-                eval_send_key(&self.window, "Space", KeyMode::Hold);
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                eval_send_key(&self.window, "Space", KeyMode::Release);
+                self.input.send_key("Space", KeyMode::Hold);
+                self.clock.sleep(Duration::from_millis(500));
+                self.input.send_key("Space", KeyMode::Release);
             }
             Movement::Move(direction, duration) => {
                 let key = match direction {
@@ -110,9 +119,9 @@ impl<'a> MovementCoordinator {
                         }
                     }
                 };
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.input.send_key(key, KeyMode::Hold);
+                self.clock.sleep(duration.to_duration(&mut self.rng));
+                self.input.send_key(key, KeyMode::Release);
             }
             Movement::Rotate(direction, duration) => {
                 let key = match direction {
@@ -126,36 +135,36 @@ impl<'a> MovementCoordinator {
                         }
                     }
                 };
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.input.send_key(key, KeyMode::Hold);
+                self.clock.sleep(duration.to_duration(&mut self.rng));
+                self.input.send_key(key, KeyMode::Release);
             }
-            Movement::Wait(duration) => thread::sleep(duration.to_duration(&mut self.rng)),
+            Movement::Wait(duration) => self.clock.sleep(duration.to_duration(&mut self.rng)),
             Movement::Type(text) => {
-                eval_send_message(&self.window, &text);
+                self.input.type_text(&text);
             }
             Movement::PressKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Press);
+                self.input.send_key(key, KeyMode::Press);
             }
             Movement::HoldKeyFor(key, duration) => {
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.input.send_key(key, KeyMode::Hold);
+                self.clock.sleep(duration.to_duration(&mut self.rng));
+                self.input.send_key(key, KeyMode::Release);
             }
             Movement::HoldKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Hold);
+                self.input.send_key(key, KeyMode::Hold);
             }
             Movement::HoldKeys(keys) => {
                 for key in keys {
-                    eval_send_key(&self.window, key, KeyMode::Hold);
+                    self.input.send_key(key, KeyMode::Hold);
                 }
             }
             Movement::ReleaseKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.input.send_key(key, KeyMode::Release);
             }
             Movement::ReleaseKeys(keys) => {
                 for key in keys {
-                    eval_send_key(&self.window, key, KeyMode::Release);
+                    self.input.send_key(key, KeyMode::Release);
                 }
             }
             Movement::Repeat(times, movements) => {
@@ -166,3 +175,32 @@ impl<'a> MovementCoordinator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::{ActionDuration, Movement, MovementCoordinator};
+    use crate::{
+        platform::RecordingInputSink,
+        utils::{Clock, ManualClock},
+    };
+
+    /// Time spent by a seeded coordinator waiting a random duration a few times
+    fn random_waits(seed: u64) -> Duration {
+        let clock = Arc::new(ManualClock::new());
+        let mut coordinator =
+            MovementCoordinator::new(Arc::new(RecordingInputSink::new()), clock.clone());
+        coordinator.set_rng_seed(seed);
+
+        let start = clock.now();
+        coordinator.play(vec![Movement::Wait(ActionDuration::Random(0..1000)); 5]);
+        clock.elapsed(start)
+    }
+
+    #[test]
+    fn test_seeded_durations() {
+        assert_eq!(random_waits(7), random_waits(7));
+        assert_ne!(random_waits(7), random_waits(8));
+    }
+}
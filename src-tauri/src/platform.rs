@@ -1,6 +1,9 @@
 pub mod shared;
 pub use self::shared::*;
 
+mod input_sink;
+pub use self::input_sink::{InputEvent, InputSink, RecordingInputSink};
+
 //
 // Windows
 //
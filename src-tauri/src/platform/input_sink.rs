@@ -0,0 +1,113 @@
+use std::{fmt, sync::Arc};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::KeyMode;
+use crate::data::Point;
+
+/// Destination of every input the bot sends to the game client.
+pub trait InputSink: fmt::Debug + Send + Sync {
+    fn send_key(&self, key: &str, mode: KeyMode);
+
+    fn mouse_move(&self, pos: Point);
+
+    /// Click at the given position (the webview only clicks while hovering an attackable target).
+    fn mouse_click(&self, pos: Point);
+
+    fn type_text(&self, text: &str);
+
+    /// Select the slot bar with its `F` key and press the slot.
+    fn send_slot(&self, slot_bar_index: usize, slot_index: usize) {
+        self.send_key(&format!("F{}", slot_bar_index + 1), KeyMode::Press);
+        self.send_key(&slot_index.to_string(), KeyMode::Press);
+    }
+}
+
+/// A single input received by a `RecordingInputSink`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key(String, KeyMode),
+    MouseMove(Point),
+    MouseClick(Point),
+    Type(String),
+}
+
+/// Input sink that remembers what it was asked to send, optionally forwarding it to another sink.
+#[derive(Debug, Default)]
+pub struct RecordingInputSink {
+    events: Mutex<Vec<InputEvent>>,
+    /// Slot bar and slot index of every `send_slot`
+    slots: Mutex<Vec<(usize, usize)>>,
+    inner: Option<Arc<dyn InputSink>>,
+}
+
+impl RecordingInputSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record inputs while still sending them to `inner`.
+    pub fn forwarding(inner: Arc<dyn InputSink>) -> Self {
+        Self {
+            events: Mutex::default(),
+            slots: Mutex::default(),
+            inner: Some(inner),
+        }
+    }
+
+    /// Get all inputs received so far.
+    pub fn events(&self) -> Vec<InputEvent> {
+        self.events.lock().clone()
+    }
+
+    /// Get all inputs received so far and forget them.
+    pub fn take(&self) -> Vec<InputEvent> {
+        std::mem::take(&mut *self.events.lock())
+    }
+
+    /// Get all slots pressed so far and forget them.
+    pub fn take_slots(&self) -> Vec<(usize, usize)> {
+        std::mem::take(&mut *self.slots.lock())
+    }
+
+    fn record(&self, event: InputEvent) {
+        self.events.lock().push(event);
+    }
+}
+
+impl InputSink for RecordingInputSink {
+    fn send_key(&self, key: &str, mode: KeyMode) {
+        self.record(InputEvent::Key(key.to_string(), mode));
+        if let Some(inner) = &self.inner {
+            inner.send_key(key, mode);
+        }
+    }
+
+    fn mouse_move(&self, pos: Point) {
+        self.record(InputEvent::MouseMove(pos));
+        if let Some(inner) = &self.inner {
+            inner.mouse_move(pos);
+        }
+    }
+
+    fn mouse_click(&self, pos: Point) {
+        self.record(InputEvent::MouseClick(pos));
+        if let Some(inner) = &self.inner {
+            inner.mouse_click(pos);
+        }
+    }
+
+    fn type_text(&self, text: &str) {
+        self.record(InputEvent::Type(text.to_string()));
+        if let Some(inner) = &self.inner {
+            inner.type_text(text);
+        }
+    }
+
+    fn send_slot(&self, slot_bar_index: usize, slot_index: usize) {
+        self.slots.lock().push((slot_bar_index, slot_index));
+        self.send_key(&format!("F{}", slot_bar_index + 1), KeyMode::Press);
+        self.send_key(&slot_index.to_string(), KeyMode::Press);
+    }
+}
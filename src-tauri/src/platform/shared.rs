@@ -1,20 +1,19 @@
 use std::time::Duration;
 
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use serde::{Deserialize, Serialize};
 use tauri::Window;
 
+use super::InputSink;
 use crate::data::Point;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum KeyMode {
     Press,
     Hold,
     Release,
 }
 
-// For visual recognition: Avoids mouse clicks outside the window by ignoring monster names that are too close to the bottom of the GUI
-pub const IGNORE_AREA_BOTTOM: u32 = 110;
-
 /// Get the native window id.
 pub fn get_window_id(window: &Window) -> Option<u64> {
     #[allow(unused_variables)]
@@ -57,16 +56,6 @@ pub fn eval_send_key(window: &Window, key: &str, mode: KeyMode) {
     }
 }
 
-pub fn send_slot_eval(window: &Window, slot_bar_index: usize, k: usize) {
-    eval_send_key(
-        window,
-        format!("F{}", slot_bar_index + 1).to_string().as_str(),
-        KeyMode::Press,
-    );
-    eval_send_key(window, k.to_string().as_str(), KeyMode::Press);
-    //std::thread::sleep(Duration::from_millis(100));
-}
-
 /* pub fn eval_mouse_click_at_point(window: &Window, pos: Point) {
     drop(
         window.eval(
@@ -143,3 +132,21 @@ pub fn eval_send_message(window: &Window, text: &str) {
         ),
     );
 }
+
+impl InputSink for Window {
+    fn send_key(&self, key: &str, mode: KeyMode) {
+        eval_send_key(self, key, mode);
+    }
+
+    fn mouse_move(&self, pos: Point) {
+        eval_mouse_move(self, pos);
+    }
+
+    fn mouse_click(&self, pos: Point) {
+        eval_mob_click(self, pos);
+    }
+
+    fn type_text(&self, text: &str) {
+        eval_send_message(self, text);
+    }
+}
@@ -0,0 +1,407 @@
+mod archive;
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{ipc::ConfigError, session::SessionRecord};
+
+/// Directory name prefix of a profile, the remainder is the profile id
+const PROFILE_PREFIX: &str = "profile_";
+
+/// File name prefix of the bot config of a profile
+const CONFIG_PREFIX: &str = ".botconfig_";
+
+/// History of the bot sessions inside the profile directory
+const SESSIONS_FILE: &str = "sessions.jsonl";
+
+const DEFAULT_PROFILE_ID: &str = "DEFAULT";
+
+const MAX_PROFILE_ID_LEN: usize = 32;
+
+/// Reason why a profile operation failed.
+#[derive(Debug)]
+pub enum ProfileError {
+    /// The app data directory could not be resolved.
+    NoDataDir,
+    /// The profile id would not make a safe file name.
+    InvalidId(String),
+    /// The profile does not exist.
+    NotFound(String),
+    /// A profile with this id already exists.
+    AlreadyExists(String),
+    /// An imported file is not a valid profile archive.
+    InvalidArchive(String),
+    /// The bot config of the profile could not be read.
+    Config(ConfigError),
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::NoDataDir => write!(f, "app data directory is unavailable"),
+            ProfileError::InvalidId(id) => write!(
+                f,
+                "invalid profile name \"{}\", use up to {} letters, digits, spaces, '-' or '_'",
+                id, MAX_PROFILE_ID_LEN
+            ),
+            ProfileError::NotFound(id) => write!(f, "profile \"{}\" does not exist", id),
+            ProfileError::AlreadyExists(id) => write!(f, "profile \"{}\" already exists", id),
+            ProfileError::InvalidArchive(reason) => {
+                write!(f, "invalid profile archive: {}", reason)
+            }
+            ProfileError::Config(err) => write!(f, "invalid bot config: {}", err),
+            ProfileError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+        }
+    }
+}
+
+/// Attach the path an io error happened on.
+trait IoContext<T> {
+    fn with_path(self, path: &Path) -> Result<T, ProfileError>;
+}
+
+impl<T> IoContext<T> for io::Result<T> {
+    fn with_path(self, path: &Path) -> Result<T, ProfileError> {
+        self.map_err(|err| ProfileError::Io(path.to_path_buf(), err))
+    }
+}
+
+/// Profiles stored in the app data directory.
+///
+/// Each profile has a `profile_<ID>` directory, which is also used as webview data directory,
+/// and a `.botconfig_<ID>` file next to it.
+#[derive(Debug, Clone)]
+pub struct ProfileStorage {
+    root: PathBuf,
+}
+
+impl ProfileStorage {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn from_app(app_handle: &tauri::AppHandle) -> Result<Self, ProfileError> {
+        app_handle
+            .path_resolver()
+            .app_data_dir()
+            .map(Self::new)
+            .ok_or(ProfileError::NoDataDir)
+    }
+
+    /// Check that `profile_id` is a valid id for a new profile.
+    pub fn validate_id(profile_id: &str) -> Result<(), ProfileError> {
+        let is_valid = !profile_id.is_empty()
+            && profile_id.len() <= MAX_PROFILE_ID_LEN
+            && profile_id.trim() == profile_id
+            && profile_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ' ');
+        if is_valid {
+            Ok(())
+        } else {
+            Err(ProfileError::InvalidId(profile_id.to_string()))
+        }
+    }
+
+    /// Check that `profile_id` can safely be used as part of a file name. Looser than
+    /// `validate_id`, profiles created before ids were checked keep working.
+    fn validate_file_name(profile_id: &str) -> Result<(), ProfileError> {
+        let is_valid = !profile_id.is_empty()
+            && profile_id != "."
+            && profile_id != ".."
+            && !profile_id
+                .chars()
+                .any(|c| c == '/' || c == '\\' || c.is_control());
+        if is_valid {
+            Ok(())
+        } else {
+            Err(ProfileError::InvalidId(profile_id.to_string()))
+        }
+    }
+
+    pub fn profile_dir(&self, profile_id: &str) -> Result<PathBuf, ProfileError> {
+        Self::validate_file_name(profile_id)?;
+        Ok(self.root.join(format!("{}{}", PROFILE_PREFIX, profile_id)))
+    }
+
+    pub fn config_path(&self, profile_id: &str) -> Result<PathBuf, ProfileError> {
+        Self::validate_file_name(profile_id)?;
+        Ok(self.root.join(format!("{}{}", CONFIG_PREFIX, profile_id)))
+    }
+
+    pub fn sessions_path(&self, profile_id: &str) -> Result<PathBuf, ProfileError> {
+        Ok(self.profile_dir(profile_id)?.join(SESSIONS_FILE))
+    }
+
+    /// Statistics of every finished bot session of a profile, oldest first.
+    pub fn session_history(&self, profile_id: &str) -> Result<Vec<SessionRecord>, ProfileError> {
+        let path = self.sessions_path(profile_id)?;
+        SessionRecord::load_history(&path).with_path(&path)
+    }
+
+    /// Directory names of every profile, e.g. `profile_DEFAULT`. Creates the default profile
+    /// if there is none yet.
+    pub fn list(&self) -> Result<Vec<String>, ProfileError> {
+        fs::create_dir_all(&self.root).with_path(&self.root)?;
+        self.migrate_legacy_paths()?;
+
+        let mut profiles = fs::read_dir(&self.root)
+            .with_path(&self.root)?
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().to_str().map(String::from))
+            .filter(|name| name.starts_with(PROFILE_PREFIX))
+            .collect::<Vec<_>>();
+        profiles.sort();
+
+        if profiles.is_empty() {
+            self.create(DEFAULT_PROFILE_ID)?;
+            profiles.push(format!("{}{}", PROFILE_PREFIX, DEFAULT_PROFILE_ID));
+        }
+
+        Ok(profiles)
+    }
+
+    pub fn create(&self, profile_id: &str) -> Result<(), ProfileError> {
+        let dir = self.vacant_profile_dir(profile_id)?;
+        fs::create_dir_all(&dir).with_path(&dir)
+    }
+
+    pub fn remove(&self, profile_id: &str) -> Result<(), ProfileError> {
+        let dir = self.existing_profile_dir(profile_id)?;
+        fs::remove_dir_all(&dir).with_path(&dir)?;
+        remove_file_if_exists(&self.config_path(profile_id)?)
+    }
+
+    pub fn rename(&self, profile_id: &str, new_profile_id: &str) -> Result<(), ProfileError> {
+        let dir = self.existing_profile_dir(profile_id)?;
+        let new_dir = self.vacant_profile_dir(new_profile_id)?;
+        fs::rename(&dir, &new_dir).with_path(&dir)?;
+
+        let config = self.config_path(profile_id)?;
+        if config.is_file() {
+            fs::rename(&config, self.config_path(new_profile_id)?).with_path(&config)?;
+        }
+        Ok(())
+    }
+
+    pub fn copy(&self, profile_id: &str, new_profile_id: &str) -> Result<(), ProfileError> {
+        let dir = self.existing_profile_dir(profile_id)?;
+        let new_dir = self.vacant_profile_dir(new_profile_id)?;
+        copy_dir_all(&dir, &new_dir).with_path(&dir)?;
+
+        let config = self.config_path(profile_id)?;
+        if config.is_file() {
+            fs::copy(&config, self.config_path(new_profile_id)?).with_path(&config)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the config, webview data and session history of a profile but keep the profile
+    /// itself.
+    pub fn reset(&self, profile_id: &str) -> Result<(), ProfileError> {
+        let dir = self.existing_profile_dir(profile_id)?;
+        fs::remove_dir_all(&dir).with_path(&dir)?;
+        remove_file_if_exists(&self.config_path(profile_id)?)?;
+        fs::create_dir_all(&dir).with_path(&dir)
+    }
+
+    fn existing_profile_dir(&self, profile_id: &str) -> Result<PathBuf, ProfileError> {
+        let dir = self.profile_dir(profile_id)?;
+        if dir.is_dir() {
+            Ok(dir)
+        } else {
+            Err(ProfileError::NotFound(profile_id.to_string()))
+        }
+    }
+
+    fn vacant_profile_dir(&self, profile_id: &str) -> Result<PathBuf, ProfileError> {
+        Self::validate_id(profile_id)?;
+        let dir = self.profile_dir(profile_id)?;
+        if dir.exists() {
+            Err(ProfileError::AlreadyExists(profile_id.to_string()))
+        } else {
+            Ok(dir)
+        }
+    }
+
+    /// Older versions built paths with `format!(r"{}\profile_{}")`. Outside of Windows this
+    /// created entries named `<root>\profile_<ID>` next to the app data directory instead of
+    /// inside it, move them where they belong.
+    fn migrate_legacy_paths(&self) -> Result<(), ProfileError> {
+        let (parent, root_name) = match (self.root.parent(), self.root.file_name()) {
+            (Some(parent), Some(root_name)) => (parent, root_name.to_string_lossy()),
+            _ => return Ok(()),
+        };
+        let legacy_prefix = format!(r"{}\", root_name);
+
+        for entry in fs::read_dir(parent).with_path(parent)?.flatten() {
+            let file_name = entry.file_name();
+            let name = match file_name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+            let legacy_path = entry.path();
+            let target_name = match name.strip_prefix(&legacy_prefix) {
+                Some("") => {
+                    // Empty directory created by the old profile listing
+                    if entry.path().is_dir() {
+                        drop(fs::remove_dir(&legacy_path));
+                    }
+                    continue;
+                }
+                Some(target_name) => target_name,
+                None => continue,
+            };
+
+            // Never overwrite data that already lives at the right place
+            let target = self.root.join(target_name);
+            if !target.exists() {
+                fs::rename(&legacy_path, &target).with_path(&legacy_path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn remove_file_if_exists(path: &Path) -> Result<(), ProfileError> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(ProfileError::Io(path.into(), err))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
+    fs::create_dir_all(&dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let ty = entry.file_type()?;
+        if ty.is_dir() {
+            copy_dir_all(entry.path(), dst.as_ref().join(entry.file_name()))?;
+        } else {
+            fs::copy(entry.path(), dst.as_ref().join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{ProfileError, ProfileStorage};
+
+    #[test]
+    fn test_validate_id() {
+        assert!(ProfileStorage::validate_id("DEFAULT").is_ok());
+        assert!(ProfileStorage::validate_id("MY BOT-2_A").is_ok());
+        for invalid in ["", " A", "A ", "..", "A/B", r"A\B", "A:B", &"A".repeat(33)] {
+            assert!(matches!(
+                ProfileStorage::validate_id(invalid),
+                Err(ProfileError::InvalidId(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_profile_lifecycle() {
+        let root = std::env::temp_dir().join(format!("neuz_profiles_{}", std::process::id()));
+        let storage = ProfileStorage::new(root.clone());
+
+        storage.create("OLD").unwrap();
+        fs::write(storage.config_path("OLD").unwrap(), "{}").unwrap();
+        assert_eq!(storage.list().unwrap(), vec!["profile_OLD"]);
+        assert!(matches!(
+            storage.create("OLD"),
+            Err(ProfileError::AlreadyExists(_))
+        ));
+
+        storage.copy("OLD", "NEW").unwrap();
+        assert!(root.join(".botconfig_NEW").is_file());
+        assert!(matches!(
+            storage.copy("OLD", "NEW"),
+            Err(ProfileError::AlreadyExists(_))
+        ));
+
+        storage.rename("NEW", "RENAMED").unwrap();
+        assert!(!root.join(".botconfig_NEW").exists());
+        assert!(root.join(".botconfig_RENAMED").is_file());
+
+        storage.reset("RENAMED").unwrap();
+        assert!(root.join("profile_RENAMED").is_dir());
+        assert!(!root.join(".botconfig_RENAMED").exists());
+
+        storage.remove("OLD").unwrap();
+        assert!(matches!(
+            storage.remove("OLD"),
+            Err(ProfileError::NotFound(_))
+        ));
+        assert_eq!(storage.list().unwrap(), vec!["profile_RENAMED"]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_existing_profile_names() {
+        let root = std::env::temp_dir().join(format!("neuz_names_{}", std::process::id()));
+        let storage = ProfileStorage::new(root.clone());
+
+        // Created by a version that did not check ids
+        let legacy_id = "Elder's farm #1 (Darkon)";
+        fs::create_dir_all(root.join(format!("profile_{}", legacy_id))).unwrap();
+        assert!(storage.config_path(legacy_id).is_ok());
+        assert!(matches!(
+            storage.create(legacy_id),
+            Err(ProfileError::InvalidId(_))
+        ));
+        assert!(matches!(
+            storage.profile_dir("../escape"),
+            Err(ProfileError::InvalidId(_))
+        ));
+
+        // It can be copied and renamed to a valid id, not to another unchecked one
+        storage.copy(legacy_id, "Elder farm").unwrap();
+        assert!(matches!(
+            storage.rename(legacy_id, "Elder's farm"),
+            Err(ProfileError::InvalidId(_))
+        ));
+        storage.rename(legacy_id, "Darkon farm").unwrap();
+        assert_eq!(
+            storage.list().unwrap(),
+            vec!["profile_Darkon farm", "profile_Elder farm"]
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_legacy_paths() {
+        let base = std::env::temp_dir().join(format!("neuz_legacy_{}", std::process::id()));
+        let root = base.join("neuz");
+        let storage = ProfileStorage::new(root.clone());
+
+        // Leftovers of the backslash paths, `<root>\<name>` next to the root
+        let legacy_path = |name: &str| base.join(format!("neuz\\{}", name));
+        fs::create_dir_all(legacy_path("profile_OLD")).unwrap();
+        fs::write(legacy_path(".botconfig_OLD"), "{}").unwrap();
+        fs::create_dir_all(legacy_path("")).unwrap();
+
+        assert_eq!(storage.list().unwrap(), vec!["profile_OLD"]);
+        assert!(root.join("profile_OLD").is_dir());
+        assert!(root.join(".botconfig_OLD").is_file());
+        // The backslash is a path separator on Windows, the old paths were right there
+        if !cfg!(windows) {
+            for name in ["profile_OLD", ".botconfig_OLD", ""] {
+                assert!(!legacy_path(name).exists());
+            }
+        }
+
+        fs::remove_dir_all(base).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{IoContext, ProfileError, ProfileStorage};
+use crate::ipc::BotConfig;
+
+/// First line of every archive
+const MAGIC: &str = "NEUZ_PROFILE";
+
+/// Bumped whenever the archive layout changes in an incompatible way
+const FORMAT_VERSION: u32 = 1;
+
+/// Profile subdirectories never exported, e.g. recorder dumps
+const EXCLUDED_DIRS: [&str; 1] = ["recordings"];
+
+/// Second line of an archive, the content of `files` follows in the same order.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format_version: u32,
+    profile_id: String,
+    /// Raw so that configs of older schema versions are migrated on import
+    bot_config: serde_json::Value,
+    files: Vec<ArchivedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedFile {
+    /// Relative to the profile directory, always '/' separated
+    path: String,
+    size: u64,
+}
+
+impl ProfileStorage {
+    /// Bundle the bot config of a profile into a single file at `path`. The webview data (login
+    /// session, client settings) is only included on request.
+    pub fn export(
+        &self,
+        profile_id: &str,
+        path: &Path,
+        include_session_data: bool,
+    ) -> Result<(), ProfileError> {
+        let dir = self.existing_profile_dir(profile_id)?;
+        let files = if include_session_data {
+            let mut files = vec![];
+            collect_files(&dir, &dir, &mut files).with_path(&dir)?;
+            files
+        } else {
+            vec![]
+        };
+
+        let manifest = Manifest {
+            format_version: FORMAT_VERSION,
+            profile_id: profile_id.to_string(),
+            bot_config: serde_json::to_value(
+                BotConfig::load(&self.config_path(profile_id)?).map_err(ProfileError::Config)?,
+            )
+            .map_err(invalid_archive)?,
+            files: files
+                .iter()
+                .map(|(path, size)| ArchivedFile {
+                    path: path.clone(),
+                    size: *size,
+                })
+                .collect(),
+        };
+
+        let result = pack(&manifest, &dir, path);
+        if result.is_err() {
+            // Do not leave half written archives behind
+            drop(fs::remove_file(path));
+        }
+        result
+    }
+
+    /// Create a new profile from an archive. Uses the exported profile id unless `profile_id`
+    /// is given and returns the id of the created profile.
+    pub fn import(&self, path: &Path, profile_id: Option<&str>) -> Result<String, ProfileError> {
+        let mut reader = BufReader::new(File::open(path).with_path(path)?);
+        let mut line = String::new();
+        reader.read_line(&mut line).with_path(path)?;
+        if line.trim_end() != MAGIC {
+            return Err(ProfileError::InvalidArchive(
+                "not a profile archive".to_string(),
+            ));
+        }
+
+        line.clear();
+        reader.read_line(&mut line).with_path(path)?;
+        let manifest: Manifest = serde_json::from_str(&line).map_err(invalid_archive)?;
+        if manifest.format_version > FORMAT_VERSION {
+            return Err(ProfileError::InvalidArchive(format!(
+                "format version {} is newer than the supported version {}",
+                manifest.format_version, FORMAT_VERSION
+            )));
+        }
+
+        let bot_config =
+            BotConfig::from_value(manifest.bot_config.clone()).map_err(ProfileError::Config)?;
+        let profile_id = profile_id.unwrap_or(&manifest.profile_id).to_string();
+        let dir = self.vacant_profile_dir(&profile_id)?;
+        let result = self.unpack(&mut reader, &manifest, &dir);
+        if result.is_ok() {
+            bot_config.serialize(&self.config_path(&profile_id)?);
+        }
+        if result.is_err() {
+            // Do not leave half imported profiles behind
+            drop(self.remove(&profile_id));
+        }
+        result.map(|_| profile_id)
+    }
+
+    fn unpack(
+        &self,
+        reader: &mut impl Read,
+        manifest: &Manifest,
+        dir: &Path,
+    ) -> Result<(), ProfileError> {
+        fs::create_dir_all(dir).with_path(dir)?;
+        for archived in &manifest.files {
+            let relative_path = safe_relative_path(&archived.path)?;
+            let file_path = dir.join(relative_path);
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent).with_path(parent)?;
+            }
+            let mut file = File::create(&file_path).with_path(&file_path)?;
+            let copied = io::copy(&mut reader.by_ref().take(archived.size), &mut file)
+                .with_path(&file_path)?;
+            if copied != archived.size {
+                return Err(ProfileError::InvalidArchive(
+                    "archive is truncated".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write the archive of `manifest` to `path`, reading the listed files from `dir`.
+fn pack(manifest: &Manifest, dir: &Path, path: &Path) -> Result<(), ProfileError> {
+    let mut writer = BufWriter::new(File::create(path).with_path(path)?);
+    writeln!(writer, "{}", MAGIC).with_path(path)?;
+    serde_json::to_writer(&mut writer, manifest).map_err(invalid_archive)?;
+    writeln!(writer).with_path(path)?;
+    for archived in &manifest.files {
+        let file_path = dir.join(&archived.path);
+        let file = File::open(&file_path).with_path(&file_path)?;
+        // The manifest is already written, files changing meanwhile must keep their listed size
+        let copied = io::copy(&mut file.take(archived.size), &mut writer).with_path(&file_path)?;
+        if copied != archived.size {
+            return Err(ProfileError::Io(
+                file_path,
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "file shrank while being exported",
+                ),
+            ));
+        }
+    }
+    writer.flush().with_path(path)
+}
+
+fn invalid_archive(err: serde_json::Error) -> ProfileError {
+    ProfileError::InvalidArchive(err.to_string())
+}
+
+/// Every file below `dir` as '/' separated path relative to `root` with its size.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, u64)>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let ty = entry.file_type()?;
+        if ty.is_dir() {
+            let is_excluded = dir == root
+                && EXCLUDED_DIRS
+                    .iter()
+                    .any(|excluded| entry.file_name() == *excluded);
+            if !is_excluded {
+                collect_files(root, &path, files)?;
+            }
+        } else if ty.is_file() {
+            let relative_path = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative_path, entry.metadata()?.len()));
+        }
+    }
+    Ok(())
+}
+
+/// Archives come from other people, never write outside of the profile directory.
+fn safe_relative_path(path: &str) -> Result<PathBuf, ProfileError> {
+    let is_safe = path.split('/').all(|segment| {
+        let mut components = Path::new(segment).components();
+        !segment.contains('\\')
+            && matches!(components.next(), Some(Component::Normal(name)) if name == segment)
+            && components.next().is_none()
+    });
+    if is_safe {
+        Ok(path.split('/').collect())
+    } else {
+        Err(ProfileError::InvalidArchive(format!(
+            "unsafe file path \"{}\"",
+            path
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{ProfileError, ProfileStorage};
+    use crate::ipc::BotConfig;
+
+    #[test]
+    fn test_export_import() {
+        let base = std::env::temp_dir().join(format!("neuz_archive_{}", std::process::id()));
+        let storage = ProfileStorage::new(base.join("neuz"));
+        storage.create("FARM").unwrap();
+        let dir = storage.profile_dir("FARM").unwrap();
+        fs::create_dir_all(dir.join("EBWebView/Default")).unwrap();
+        fs::write(dir.join("EBWebView/Default/Cookies"), [1, 2, 3]).unwrap();
+        fs::create_dir_all(dir.join("recordings/session_1")).unwrap();
+        fs::write(dir.join("recordings/session_1/ticks.jsonl"), "{}").unwrap();
+        BotConfig::default()
+            .changed()
+            .serialize(&storage.config_path("FARM").unwrap());
+
+        let without_session = base.join("without_session.neuz");
+        storage.export("FARM", &without_session, false).unwrap();
+        assert_eq!(
+            storage.import(&without_session, Some("SHARED")).unwrap(),
+            "SHARED"
+        );
+        let shared_dir = storage.profile_dir("SHARED").unwrap();
+        assert!(!shared_dir.join("EBWebView").exists());
+        let config = BotConfig::load(&storage.config_path("SHARED").unwrap()).unwrap();
+        assert_eq!(config.change_id(), 1);
+
+        let with_session = base.join("with_session.neuz");
+        storage.export("FARM", &with_session, true).unwrap();
+        assert!(matches!(
+            storage.import(&with_session, None),
+            Err(ProfileError::AlreadyExists(_))
+        ));
+        storage.remove("FARM").unwrap();
+        assert_eq!(storage.import(&with_session, None).unwrap(), "FARM");
+        assert_eq!(
+            fs::read(dir.join("EBWebView/Default/Cookies")).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert!(!dir.join("recordings").exists());
+
+        // Truncated archives are rejected without leaving a profile behind
+        let archive = fs::read(&with_session).unwrap();
+        fs::write(&with_session, &archive[..archive.len() - 1]).unwrap();
+        assert!(matches!(
+            storage.import(&with_session, Some("BROKEN")),
+            Err(ProfileError::InvalidArchive(_))
+        ));
+        assert!(!storage.profile_dir("BROKEN").unwrap().exists());
+
+        fs::write(&with_session, "{}").unwrap();
+        assert!(matches!(
+            storage.import(&with_session, Some("BROKEN")),
+            Err(ProfileError::InvalidArchive(_))
+        ));
+
+        fs::remove_dir_all(base).unwrap();
+    }
+
+    #[test]
+    fn test_safe_relative_path() {
+        assert!(super::safe_relative_path("EBWebView/Default/Cookies").is_ok());
+        for unsafe_path in ["", "../escape", "/etc/passwd", "a/../../b", r"..\escape"] {
+            assert!(super::safe_relative_path(unsafe_path).is_err());
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
+
+use libscreenshot::ImageBuffer;
+use serde::Serialize;
+
+use crate::{data::ClientStats, platform::InputEvent, utils::Clock};
+
+/// Player and target stats as read during a tick.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct StatsSnapshot {
+    hp: u32,
+    mp: u32,
+    fp: u32,
+    target_hp: u32,
+    target_mp: u32,
+}
+
+impl From<&ClientStats> for StatsSnapshot {
+    fn from(stats: &ClientStats) -> Self {
+        Self {
+            hp: stats.hp.value,
+            mp: stats.mp.value,
+            fp: stats.fp.value,
+            target_hp: stats.target_hp.value,
+            target_mp: stats.target_mp.value,
+        }
+    }
+}
+
+/// Line of the `ticks.jsonl` file of a dump.
+#[derive(Debug, Serialize)]
+struct TickRecord {
+    tick: u64,
+    elapsed_ms: u128,
+    state: String,
+    stats: StatsSnapshot,
+    inputs: Vec<InputEvent>,
+    frame: Option<String>,
+}
+
+struct RecordedTick {
+    record: TickRecord,
+    frame: Option<ImageBuffer>,
+}
+
+/// Keeps the last captured frames and decisions in memory so they can be written to disk
+/// when something goes wrong.
+pub struct Recorder {
+    session_dir: PathBuf,
+    capacity: usize,
+    clock: Arc<dyn Clock>,
+    started: Instant,
+    tick_count: u64,
+    dump_count: u32,
+    ticks: VecDeque<RecordedTick>,
+}
+
+impl Recorder {
+    /// Create a disabled recorder, dumps are written to `session_dir`.
+    pub fn new(session_dir: PathBuf, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            session_dir,
+            capacity: 0,
+            started: clock.now(),
+            clock,
+            tick_count: 0,
+            dump_count: 0,
+            ticks: VecDeque::new(),
+        }
+    }
+
+    /// Number of ticks to keep, 0 disables the recorder.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.ticks.len() > capacity {
+            self.ticks.pop_front();
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        frame: Option<&ImageBuffer>,
+        state: String,
+        stats: &ClientStats,
+        inputs: Vec<InputEvent>,
+    ) {
+        self.tick_count += 1;
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.ticks.len() >= self.capacity {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back(RecordedTick {
+            record: TickRecord {
+                tick: self.tick_count,
+                elapsed_ms: self.clock.elapsed(self.started).as_millis(),
+                state,
+                stats: stats.into(),
+                inputs,
+                frame: None,
+            },
+            frame: frame.cloned(),
+        });
+    }
+
+    /// Write every recorded tick into a new directory of the session and forget them.
+    pub fn dump(&mut self, reason: &str) -> io::Result<Option<PathBuf>> {
+        if self.ticks.is_empty() {
+            return Ok(None);
+        }
+
+        self.dump_count += 1;
+        let dump_dir = self
+            .session_dir
+            .join(format!("{:03}_{}", self.dump_count, reason));
+        fs::create_dir_all(&dump_dir)?;
+
+        let mut ticks_file = BufWriter::new(File::create(dump_dir.join("ticks.jsonl"))?);
+        for mut tick in self.ticks.drain(..) {
+            if let Some(frame) = tick.frame {
+                let file_name = format!("frame_{:06}.png", tick.record.tick);
+                frame
+                    .save(dump_dir.join(&file_name))
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                tick.record.frame = Some(file_name);
+            }
+            serde_json::to_writer(&mut ticks_file, &tick.record)?;
+            writeln!(ticks_file)?;
+        }
+        ticks_file.flush()?;
+
+        Ok(Some(dump_dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Arc, time::Duration};
+
+    use super::Recorder;
+    use crate::{
+        data::ClientStats,
+        platform::{InputEvent, KeyMode},
+        utils::ManualClock,
+    };
+
+    #[test]
+    fn test_rolling_dump() {
+        let session_dir =
+            std::env::temp_dir().join(format!("neuz_recorder_{}", std::process::id()));
+        let clock = Arc::new(ManualClock::new());
+        let mut recorder = Recorder::new(session_dir.clone(), clock.clone());
+        let frame = image::RgbaImage::new(4, 4);
+        let mut stats = ClientStats::new(None);
+
+        // Disabled by default
+        recorder.record(
+            Some(&frame),
+            "SearchingForEnemy".to_string(),
+            &stats,
+            vec![],
+        );
+        assert!(recorder.dump("death").unwrap().is_none());
+
+        recorder.set_capacity(2);
+        for hp in [100, 50, 10] {
+            clock.advance(Duration::from_millis(100));
+            stats.hp.value = hp;
+            recorder.record(
+                Some(&frame),
+                "Attacking".to_string(),
+                &stats,
+                vec![InputEvent::Key("Z".to_string(), KeyMode::Press)],
+            );
+        }
+
+        let dump_dir = recorder.dump("death").unwrap().unwrap();
+        assert_eq!(dump_dir, session_dir.join("001_death"));
+        assert!(dump_dir.join("frame_000003.png").is_file());
+        assert!(dump_dir.join("frame_000004.png").is_file());
+        assert!(!dump_dir.join("frame_000002.png").exists());
+
+        let ticks = fs::read_to_string(dump_dir.join("ticks.jsonl")).unwrap();
+        let ticks = ticks
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ticks.len(), 2);
+        assert_eq!(ticks[0]["stats"]["hp"], 50);
+        assert_eq!(ticks[1]["elapsed_ms"], 300);
+        assert_eq!(ticks[1]["inputs"][0]["Key"][0], "Z");
+        assert_eq!(ticks[1]["frame"], "frame_000004.png");
+
+        // The buffer starts over after a dump
+        assert!(recorder.dump("mobs_timeout").unwrap().is_none());
+
+        fs::remove_dir_all(session_dir).unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ipc::{BotMode, SlotType};
+
+/// Statistics of one engagement of the bot, from start until it is stopped, the mode is changed
+/// or the client closes. Timestamps are unix seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub mode: BotMode,
+    pub kills: u32,
+    pub deaths: u32,
+    /// Slots pressed by the bot, e.g. pots and skills
+    pub slot_uses: BTreeMap<SlotType, u32>,
+    /// Milliseconds spent in each behavior state
+    pub state_durations: BTreeMap<String, u64>,
+}
+
+impl SessionRecord {
+    /// Append the record to the history file at `path`, one JSON object per line.
+    pub fn append_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+
+        // Finish a line cut off by a crash, the record would be lost along with it otherwise
+        let mut line = String::new();
+        if file.metadata()?.len() > 0 {
+            let mut last_byte = [0; 1];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last_byte)?;
+            if last_byte[0] != b'\n' {
+                line.push('\n');
+            }
+        }
+        line.push_str(&serde_json::to_string(self)?);
+        line.push('\n');
+        file.write_all(line.as_bytes())
+    }
+
+    /// Read every record of the history file at `path`, oldest first. A missing file is an empty
+    /// history, lines that cannot be parsed (e.g. cut off by a crash) are skipped.
+    pub fn load_history(path: &Path) -> io::Result<Vec<SessionRecord>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Builds the `SessionRecord` of the running session tick by tick.
+#[derive(Debug)]
+pub struct SessionTracker {
+    record: SessionRecord,
+    /// Kill count of the behavior when the session started
+    initial_kill_count: u32,
+    state: Option<(String, Instant)>,
+}
+
+impl SessionTracker {
+    pub fn new(mode: BotMode, started_at: u64, initial_kill_count: u32) -> Self {
+        Self {
+            record: SessionRecord {
+                started_at,
+                ended_at: started_at,
+                mode,
+                kills: 0,
+                deaths: 0,
+                slot_uses: BTreeMap::new(),
+                state_durations: BTreeMap::new(),
+            },
+            initial_kill_count,
+            state: None,
+        }
+    }
+
+    pub fn mode(&self) -> &BotMode {
+        &self.record.mode
+    }
+
+    /// Kill count of the behavior, which keeps counting across sessions.
+    pub fn set_kill_count(&mut self, kill_count: u32) {
+        self.record.kills = kill_count.saturating_sub(self.initial_kill_count);
+    }
+
+    pub fn record_death(&mut self) {
+        self.record.deaths += 1;
+    }
+
+    pub fn record_slot_use(&mut self, slot_type: SlotType) {
+        *self.record.slot_uses.entry(slot_type).or_insert(0) += 1;
+    }
+
+    /// The behavior is in `state` from `now` on, the time since the last call is added to the
+    /// previous state.
+    pub fn enter_state(&mut self, state: &str, now: Instant) {
+        self.close_state(now);
+        self.state = Some((state.to_string(), now));
+    }
+
+    pub fn finish(mut self, ended_at: u64, now: Instant) -> SessionRecord {
+        self.close_state(now);
+        self.record.ended_at = ended_at.max(self.record.started_at);
+        self.record
+    }
+
+    fn close_state(&mut self, now: Instant) {
+        if let Some((state, since)) = self.state.take() {
+            let elapsed = now.saturating_duration_since(since).as_millis();
+            let total = self.record.state_durations.entry(state).or_insert(0);
+            *total = total.saturating_add(elapsed.try_into().unwrap_or(u64::MAX));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{SessionRecord, SessionTracker};
+    use crate::ipc::{BotMode, SlotType};
+
+    #[test]
+    fn test_session_tracker() {
+        let start = Instant::now();
+        let mut tracker = SessionTracker::new(BotMode::Farming, 1_000, 7);
+
+        tracker.enter_state("SearchingForEnemy", start);
+        tracker.enter_state("Attacking", start + Duration::from_millis(300));
+        tracker.record_slot_use(SlotType::Food);
+        tracker.record_slot_use(SlotType::Food);
+        tracker.record_slot_use(SlotType::AttackSkill);
+        tracker.enter_state("SearchingForEnemy", start + Duration::from_millis(1_000));
+        tracker.set_kill_count(9);
+        tracker.record_death();
+
+        let record = tracker.finish(1_060, start + Duration::from_millis(1_500));
+        assert_eq!(record.mode, BotMode::Farming);
+        assert_eq!((record.started_at, record.ended_at), (1_000, 1_060));
+        assert_eq!((record.kills, record.deaths), (2, 1));
+        assert_eq!(record.slot_uses[&SlotType::Food], 2);
+        assert_eq!(record.slot_uses[&SlotType::AttackSkill], 1);
+        assert_eq!(record.state_durations["SearchingForEnemy"], 800);
+        assert_eq!(record.state_durations["Attacking"], 700);
+    }
+
+    #[test]
+    fn test_history() {
+        let path = std::env::temp_dir()
+            .join(format!("neuz_sessions_{}", std::process::id()))
+            .join("sessions.jsonl");
+        assert!(SessionRecord::load_history(&path).unwrap().is_empty());
+
+        let mut tracker = SessionTracker::new(BotMode::Support, 10, 0);
+        tracker.record_slot_use(SlotType::BuffSkill);
+        let first = tracker.finish(20, Instant::now());
+        let second = SessionTracker::new(BotMode::Farming, 30, 0).finish(40, Instant::now());
+        let third = SessionTracker::new(BotMode::Farming, 50, 0).finish(60, Instant::now());
+        first.append_to(&path).unwrap();
+        second.append_to(&path).unwrap();
+
+        // A session cut off while writing does not hide the others
+        let mut content = std::fs::read_to_string(&path).unwrap();
+        content.push_str("{\"started_at\":");
+        std::fs::write(&path, content).unwrap();
+        assert_eq!(
+            SessionRecord::load_history(&path).unwrap(),
+            vec![first.clone(), second.clone()]
+        );
+
+        // Nor the sessions saved after it
+        third.append_to(&path).unwrap();
+        assert_eq!(
+            SessionRecord::load_history(&path).unwrap(),
+            vec![first, second, third]
+        );
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
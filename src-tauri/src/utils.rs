@@ -1,4 +1,12 @@
+mod clock;
 mod datetime;
+pub mod metrics;
+mod tick_scheduler;
 mod timer;
 
-pub use self::{datetime::DateTime, timer::Timer};
+pub use self::{
+    clock::{Clock, ManualClock, SystemClock},
+    datetime::DateTime,
+    tick_scheduler::{TickScheduler, TickStats},
+    timer::Timer,
+};
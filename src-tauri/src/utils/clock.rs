@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// Source of time for everything that waits or measures durations.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    fn sleep(&self, duration: Duration);
+
+    /// Time elapsed since `since`, zero if `since` is in the future.
+    fn elapsed(&self, since: Instant) -> Duration {
+        self.now().saturating_duration_since(since)
+    }
+}
+
+/// Wall clock, sleeping blocks the current thread.
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Simulated clock which only moves forward when slept on or advanced by hand.
+#[derive(Debug)]
+pub struct ManualClock {
+    start: Instant,
+    offset: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.offset.lock() += duration;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.offset.lock()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
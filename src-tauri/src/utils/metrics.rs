@@ -0,0 +1,196 @@
+use std::{fs, io, path::Path, time::Duration};
+
+use parking_lot::{const_mutex, Mutex};
+use serde::Serialize;
+
+/// Each power of two is split into 2^3 buckets, percentiles are accurate to about 1/8th
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: u32 = 1 << SUB_BUCKET_BITS;
+
+/// Covers up to 2^40µs, far beyond anything a tick measures
+const BUCKETS: usize = 40 * SUB_BUCKETS as usize;
+
+static GLOBAL: MetricsRegistry = MetricsRegistry::new();
+
+/// Registry fed by every `Timer`.
+pub fn global() -> &'static MetricsRegistry {
+    &GLOBAL
+}
+
+/// Log-linear histogram of durations in microseconds.
+#[derive(Debug, Clone)]
+struct Histogram {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: vec![0; BUCKETS],
+            count: 0,
+            sum: 0,
+            max: 0,
+        }
+    }
+
+    fn record(&mut self, micros: u64) {
+        self.buckets[bucket_index(micros)] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(micros);
+        self.max = self.max.max(micros);
+    }
+
+    /// Upper bound of the bucket holding the `percentile`th sample, never above the maximum.
+    fn percentile(&self, percentile: f64) -> u64 {
+        let rank = ((self.count as f64 * percentile / 100.0).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_upper_bound(index).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+fn bucket_index(micros: u64) -> usize {
+    if micros < SUB_BUCKETS as u64 {
+        return micros as usize;
+    }
+    // The highest bit selects the power of two, the bits below it the sub-bucket
+    let exponent = 63 - micros.leading_zeros() - SUB_BUCKET_BITS;
+    let sub_bucket = (micros >> exponent) as u32 - SUB_BUCKETS;
+    (((exponent + 1) * SUB_BUCKETS + sub_bucket) as usize).min(BUCKETS - 1)
+}
+
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u32;
+    if index < SUB_BUCKETS {
+        return index as u64;
+    }
+    let exponent = index / SUB_BUCKETS - 1;
+    let sub_bucket = index % SUB_BUCKETS + SUB_BUCKETS;
+    ((sub_bucket as u64 + 1) << exponent) - 1
+}
+
+/// Timing summary of one label, durations in milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricSummary {
+    pub label: String,
+    pub count: u64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+/// Durations recorded per label, e.g. `capture_window` or `main_loop`.
+pub struct MetricsRegistry {
+    histograms: Mutex<Vec<(String, Histogram)>>,
+}
+
+impl Default for MetricsRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsRegistry {
+    pub const fn new() -> Self {
+        Self {
+            histograms: const_mutex(Vec::new()),
+        }
+    }
+
+    pub fn record(&self, label: &str, duration: Duration) {
+        let micros = duration.as_micros().try_into().unwrap_or(u64::MAX);
+        let mut histograms = self.histograms.lock();
+        match histograms.iter_mut().find(|(name, _)| name == label) {
+            Some((_, histogram)) => histogram.record(micros),
+            None => {
+                let mut histogram = Histogram::new();
+                histogram.record(micros);
+                histograms.push((label.to_string(), histogram));
+            }
+        }
+    }
+
+    /// Summary of every label sorted by name.
+    pub fn summaries(&self) -> Vec<MetricSummary> {
+        let to_millis = |micros: u64| micros as f64 / 1000.0;
+        let mut summaries = self
+            .histograms
+            .lock()
+            .iter()
+            .map(|(label, histogram)| MetricSummary {
+                label: label.clone(),
+                count: histogram.count,
+                mean: to_millis(histogram.sum / histogram.count.max(1)),
+                p50: to_millis(histogram.percentile(50.0)),
+                p95: to_millis(histogram.percentile(95.0)),
+                max: to_millis(histogram.max),
+            })
+            .collect::<Vec<_>>();
+        summaries.sort_by(|a, b| a.label.cmp(&b.label));
+        summaries
+    }
+
+    pub fn reset(&self) {
+        self.histograms.lock().clear();
+    }
+
+    /// Write the summaries to `path` as JSON.
+    pub fn dump(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&self.summaries())?;
+        fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{bucket_index, bucket_upper_bound, MetricsRegistry};
+
+    #[test]
+    fn test_buckets() {
+        let mut last_index = 0;
+        for micros in 0..100_000 {
+            let index = bucket_index(micros);
+            assert!(index >= last_index);
+            assert!(bucket_upper_bound(index) >= micros);
+            // Within an eighth of the sample
+            assert!(bucket_upper_bound(index) - micros <= micros / 8);
+            last_index = index;
+        }
+    }
+
+    #[test]
+    fn test_summaries() {
+        let registry = MetricsRegistry::new();
+        for millis in 1..=100 {
+            registry.record("capture_window", Duration::from_millis(millis));
+        }
+        registry.record("identify_mobs", Duration::from_micros(1500));
+
+        let summaries = registry.summaries();
+        assert_eq!(summaries.len(), 2);
+        let capture = &summaries[0];
+        assert_eq!(capture.label, "capture_window");
+        assert_eq!(capture.count, 100);
+        assert_eq!(capture.max, 100.0);
+        assert!((50.0..=50.0 * 1.125).contains(&capture.p50));
+        assert!((95.0..=100.0).contains(&capture.p95));
+        assert_eq!(summaries[1].p50, 1.5);
+
+        registry.reset();
+        assert!(registry.summaries().is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::{Clock, Timer};
+
+/// Duration and pacing of the last finished tick.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TickStats {
+    pub duration: Duration,
+    /// `None` when ticks run back to back
+    pub budget: Option<Duration>,
+    /// Ticks that took longer than their budget since the scheduler was created
+    pub missed_deadlines: u64,
+}
+
+/// Paces the main loop to a target tick rate by sleeping away what is left of each tick's budget.
+pub struct TickScheduler {
+    clock: Arc<dyn Clock>,
+    budget: Option<Duration>,
+    tick_start: Option<Instant>,
+    stats: TickStats,
+}
+
+impl TickScheduler {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            budget: None,
+            tick_start: None,
+            stats: TickStats::default(),
+        }
+    }
+
+    /// Ticks per second, 0 runs ticks as fast as capture allows.
+    pub fn set_tick_rate(&mut self, tick_rate: u32) {
+        self.budget = if tick_rate == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(1) / tick_rate)
+        };
+    }
+
+    /// Begin a tick, the returned timer reports by how much the tick overran its budget.
+    pub fn start_tick(&mut self, label: &str) -> Timer {
+        self.tick_start = Some(self.clock.now());
+        Timer::start_new(label).with_budget(self.budget)
+    }
+
+    /// End the current tick and wait until the next one is due. Missed deadlines are counted
+    /// and the next tick starts right away instead of trying to catch up.
+    pub fn finish_tick(&mut self) {
+        let tick_start = match self.tick_start.take() {
+            Some(tick_start) => tick_start,
+            None => return,
+        };
+        let duration = self.clock.elapsed(tick_start);
+        self.stats.duration = duration;
+        self.stats.budget = self.budget;
+
+        if let Some(budget) = self.budget {
+            if duration > budget {
+                self.stats.missed_deadlines += 1;
+            } else {
+                self.clock.sleep(budget - duration);
+            }
+        }
+    }
+
+    /// Abandon the current tick without waiting, e.g. while the bot is idle.
+    pub fn skip_tick(&mut self) {
+        self.tick_start = None;
+    }
+
+    pub fn stats(&self) -> TickStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::TickScheduler;
+    use crate::utils::{Clock, ManualClock};
+
+    #[test]
+    fn test_tick_pacing() {
+        let clock = Arc::new(ManualClock::new());
+        let mut scheduler = TickScheduler::new(clock.clone());
+        scheduler.set_tick_rate(10);
+
+        // Fast ticks wait for the rest of their budget
+        let start = clock.now();
+        scheduler.start_tick("tick").silence();
+        clock.advance(Duration::from_millis(30));
+        scheduler.finish_tick();
+        assert_eq!(clock.elapsed(start), Duration::from_millis(100));
+        assert_eq!(scheduler.stats().duration, Duration::from_millis(30));
+        assert_eq!(scheduler.stats().missed_deadlines, 0);
+
+        // Slow ticks are counted and the next one starts right away
+        let start = clock.now();
+        scheduler.start_tick("tick").silence();
+        clock.advance(Duration::from_millis(150));
+        scheduler.finish_tick();
+        assert_eq!(clock.elapsed(start), Duration::from_millis(150));
+        assert_eq!(scheduler.stats().missed_deadlines, 1);
+
+        // Unlimited rate never waits
+        scheduler.set_tick_rate(0);
+        let start = clock.now();
+        scheduler.start_tick("tick").silence();
+        scheduler.finish_tick();
+        assert_eq!(clock.elapsed(start), Duration::ZERO);
+        assert_eq!(scheduler.stats().budget, None);
+    }
+}
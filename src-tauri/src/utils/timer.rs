@@ -3,11 +3,12 @@ use std::{
     time::{Duration, Instant},
 };
 
-//use slog::Logger;
+use super::metrics;
 
 pub struct Timer {
     label: String,
     start: Instant,
+    budget: Option<Duration>,
     is_silenced: RefCell<bool>,
 }
 
@@ -19,25 +20,31 @@ impl Timer {
         Timer {
             label: label.to_string(),
             start: Instant::now(),
+            budget: None,
             is_silenced: RefCell::new(false),
         }
     }
 
+    /// Time the measured work is allowed to take, overruns are recorded as `<label>.overrun`.
+    pub fn with_budget(mut self, budget: Option<Duration>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Time by which the budget has been exceeded so far.
+    pub fn overrun(&self) -> Option<Duration> {
+        self.budget
+            .and_then(|budget| self.elapsed().checked_sub(budget))
+            .filter(|overrun| !overrun.is_zero())
+    }
+
+    /// Record the time elapsed so far under `label@file:line`.
     #[allow(dead_code)]
     pub fn lap(&self, file: &'static str, line: u32) {
         if *self.is_silenced.borrow() {
             return;
         }
-        if std::env::var("NEUZ_TIMERS").is_err() {
-            return;
-        }
-        println!(
-            "[{} {}${}] took {:?}",
-            self.label,
-            file,
-            line,
-            self.elapsed()
-        );
+        metrics::global().record(&format!("{}@{}:{}", self.label, file, line), self.elapsed());
     }
 
     pub fn silence(&self) {
@@ -48,14 +55,15 @@ impl Timer {
         self.start.elapsed()
     }
 
+    /// Feed the elapsed time to the metrics registry, silenced timers are not recorded.
     pub fn report(&self) {
         if *self.is_silenced.borrow() {
             return;
         }
-        if std::env::var("NEUZ_TIMERS").is_err() {
-            return;
+        metrics::global().record(&self.label, self.elapsed());
+        if let Some(overrun) = self.overrun() {
+            metrics::global().record(&format!("{}.overrun", self.label), overrun);
         }
-        println!("[{}] took {:?}", self.label, self.elapsed());
     }
 }
 
@@ -10,6 +10,7 @@ import MissionControl from './MissionControl'
 import { randomNumberInRange } from './components/utils/RandomInt'
 import useModal from './components/utils/UseModal'
 import { getVersion } from '@tauri-apps/api/app'
+import { open, save } from '@tauri-apps/api/dialog'
 import YesNoModal from './components/YesNoModal'
 import ConfigLabel from './components/config/ConfigLabel'
 import ConfigTableRow from './components/config/ConfigTableRow'
@@ -17,6 +18,10 @@ import ConfigPanel from './components/config/ConfigPanel'
 import ConfigTable from './components/config/ConfigTable'
 import ProfileDisplay from './components/ProfileDisplay'
 import TextInput from './components/config/TextInput'
+import BooleanSlider from './components/config/BooleanSlider'
+import Modal from './components/Modal'
+import SessionHistory from './components/SessionHistory'
+import { SessionRecordModel } from './models/FrontendInfo'
 
 const launcherBackgrounds = [LauncherBackground, LauncherBackground2]
 const Greetings = [
@@ -80,6 +85,8 @@ const Launcher = ({ className }: Props) => {
     const renameProfileModal = useModal()
     const copyProfileModal = useModal()
     const resetProfileModal = useModal()
+    const exportProfileModal = useModal()
+    const historyModal = useModal()
 
 
     const delProfileModal = useModal()
@@ -88,13 +95,16 @@ const Launcher = ({ className }: Props) => {
     const [idList, setList] = useState<string[]>(["DEFAULT"])
     const [currentPage,setPage] = useState(1)
     const [newProfile,setNewProfile] = useState("")
+    const [profileError, setProfileError] = useState("")
+    const [includeSessionData, setIncludeSessionData] = useState(false)
+    const [sessions, setSessions] = useState<SessionRecordModel[]>([])
 
     const launch = () => {
         if (!hasEnteredMainLoop && profileId !== "" ) {
             enterMainLoop()
             invoke('create_window',{profileId: profileId}).then(()=> {
-                invoke('start_bot',{profileId: profileId}).then(()=> {setIsLaunched(true)})
-            })
+                invoke('start_bot',{profileId: profileId}).then(()=> {setIsLaunched(true)}).catch(setProfileError)
+            }).catch(setProfileError)
 
         }
     }
@@ -122,6 +132,34 @@ const Launcher = ({ className }: Props) => {
     const refreshProfiles = () => {
         invoke('get_profiles').then((value: any)=> {
             if(value) setList(value);
+            setProfileError("")
+        }).catch(setProfileError)
+    }
+
+    // Profile commands validate names and report failures, the list is reloaded from disk
+    const updateProfiles = (command: string, args: Record<string, string>) => {
+        invoke(command, args).then(refreshProfiles).catch(setProfileError)
+    }
+
+    const profileArchiveFilters = [{ name: 'Neuz profile', extensions: ['neuzprofile'] }]
+
+    const exportProfile = () => {
+        save({ defaultPath: `${profileId}.neuzprofile`, filters: profileArchiveFilters }).then((path) => {
+            if (path) invoke('export_profile', { profileId, path, includeSessionData }).then(() => setProfileError("")).catch(setProfileError)
+        })
+    }
+
+    const showHistory = () => {
+        invoke('session_history', { profileId }).then((value) => {
+            setSessions(value as SessionRecordModel[])
+            setProfileError("")
+            historyModal.open()
+        }).catch(setProfileError)
+    }
+
+    const importProfile = () => {
+        open({ filters: profileArchiveFilters }).then((path) => {
+            if (typeof path === 'string') updateProfiles('import_profile', { path })
         })
     }
 
@@ -152,8 +190,7 @@ const Launcher = ({ className }: Props) => {
                     }
                     onYes={() => {
                         if (newProfile.length > 0 && !idList.includes("profile_" + newProfile.toUpperCase())){
-                            invoke('create_profile', {profileId:newProfile.toUpperCase()})
-                            setList((oldValue) => [...oldValue, "profile_" + newProfile.toUpperCase()])
+                            updateProfiles('create_profile', {profileId:newProfile.toUpperCase()})
                         }
                         setNewProfile("")
                     }}
@@ -166,8 +203,8 @@ const Launcher = ({ className }: Props) => {
                     }
                     onYes={() => {
                         if (!idList.includes("profile_" + newProfile.toUpperCase())){
-                            invoke('rename_profile', {profileId: profileId, newProfileId: newProfile})
-                            setList((oldValue) => [...oldValue.filter((val) => val.replaceAll("profile_", "") !== profileId), "profile_" + newProfile.toUpperCase()])
+                            updateProfiles('rename_profile', {profileId: profileId, newProfileId: newProfile.toUpperCase()})
+                            setPID("")
                         }
                         setNewProfile("")
                     }}
@@ -180,8 +217,7 @@ const Launcher = ({ className }: Props) => {
                     }
                     onYes={() => {
                         if (!idList.includes("profile_" + newProfile.toUpperCase())){
-                            invoke('copy_profile', {profileId: profileId, newProfileId: newProfile})
-                            setList((oldValue) => [...oldValue, "profile_" + newProfile.toUpperCase()])
+                            updateProfiles('copy_profile', {profileId: profileId, newProfileId: newProfile.toUpperCase()})
                         }
                         setNewProfile("")
                     }}
@@ -193,20 +229,37 @@ const Launcher = ({ className }: Props) => {
                        <h3>This action cant be undone</h3>
                     }
                     onYes={() => {
-                        invoke('remove_profile', {profileId: profileId})
-                        setList((oldValue) => oldValue.filter((filtred) => filtred.replaceAll("profile_","") !== profileId))
+                        updateProfiles('remove_profile', {profileId: profileId})
                         setPage(1)
                         setPID("")
                     }}
                 />
 
+                <YesNoModal isShowing={exportProfileModal.isShown} hide={exportProfileModal.close}
+                    title={<h4>Export profile {profileId}</h4>}
+                    body={
+                        <ConfigTable>
+                            <ConfigTableRow
+                                label={<ConfigLabel name="Include session data" helpText="Also export the game client data like your login session, only share it with people you trust" />}
+                                item={<BooleanSlider value={includeSessionData} onChange={setIncludeSessionData} />}
+                            />
+                        </ConfigTable>
+                    }
+                    onYes={exportProfile}
+                />
+
+                <Modal isShowing={historyModal.isShown} hide={historyModal.close}
+                    title={<h4>Sessions of profile {profileId}</h4>}
+                    body={<SessionHistory sessions={sessions} />}
+                />
+
                 <YesNoModal isShowing={resetProfileModal.isShown} hide={resetProfileModal.close}
                     title={<h4>Do you want to reset this profile ?</h4>}
                     body={
                        <h3>This action cant be undone</h3>
                     }
                     onYes={() => {
-                        invoke('reset_profile', {profileId: profileId})
+                        updateProfiles('reset_profile', {profileId: profileId})
                     }}
                 />
 
@@ -229,6 +282,9 @@ const Launcher = ({ className }: Props) => {
                                                 <div className="btn m" onClick={()=> {profileId !== null && copyProfileModal.open()}}>Copy</div>
                                                 <div className="btn m" onClick={()=> {profileId !== null && delProfileModal.open()}}>Remove</div>
                                                 <div className="btn m" onClick={()=> {profileId !== null && resetProfileModal.open()}}>Reset</div>
+                                                <div className="btn m" onClick={()=> {profileId !== "" && exportProfileModal.open()}}>Export</div>
+                                                <div className="btn m" onClick={importProfile}>Import</div>
+                                                <div className="btn m" onClick={()=> {profileId !== "" && showHistory()}}>History</div>
                                                 <div className="btn m" onClick={refreshProfiles}>Refresh</div>
                                             </div>
 
@@ -244,6 +300,7 @@ const Launcher = ({ className }: Props) => {
                                                     <div  style={{width: "50%", fontSize: "1.5rem"}} className="btn sm" onClick={()=> {setPage((current) => current === Math.ceil(idList.length / 4)? current: current +1)}}>{"->"}</div>
                                                 </div>
                                             </table>
+                                            {profileError && <div className="error">{profileError}</div>}
                                         </>
                                     }
                                 />
@@ -277,6 +334,11 @@ export default styled(Launcher)`
     background-position: center center;
     background-size: cover;
 
+    & .error {
+        color: hsl(0, 100%, 70%);
+        text-align: center;
+    }
+
     & .container {
         display: flex;
         flex-direction: column;
@@ -10,14 +10,25 @@ import ImageFarm from './assets/btn_leveling.png'
 import ImageSupport from './assets/btn_full_support.png'
 import ImageShout from './assets/btn_shout.png'
 
-import { BotConfigModel, ModeModel } from './models/BotConfig'
+import { BotConfigModel, ConfigIssueModel, ModeModel } from './models/BotConfig'
 import FarmingConfig from "./components/behaviors/FarmingConfig"
 import SupportConfig from "./components/behaviors/SupportConfig"
 import ShoutConfig from "./components/behaviors/ShoutConfig"
 import Footer from "./components/Footer"
-import { FrontendInfoModel } from "./models/FrontendInfo"
+import DebugOverlay from "./components/DebugOverlay"
+import ConfigIssues from "./components/ConfigIssues"
+import SlotUsageAlert from "./components/SlotUsageAlert"
+import TimingMetrics from "./components/TimingMetrics"
+import Modal from "./components/Modal"
+import useModal from "./components/utils/UseModal"
+import { FrontendInfoModel, MetricSummaryModel } from "./models/FrontendInfo"
 import { invoke } from "@tauri-apps/api"
+import { message } from "@tauri-apps/api/dialog"
 import { useStopWatch } from "./components/utils/StopWatch"
+import ConfigTable from "./components/config/ConfigTable"
+import ConfigTableRow from "./components/config/ConfigTableRow"
+import ConfigLabel from "./components/config/ConfigLabel"
+import NumericInput from "./components/config/NumericInput"
 
 //type Bounds = {x: number, y: number, w: number, h: number}
 
@@ -31,6 +42,9 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
     //const [imageData, setImageData] = useState({ data: '', width: 0, height: 0 })
     const [info, setInfo] = useState<FrontendInfoModel | null>(null);
     const [config, setConfig] = useState<BotConfigModel | null>(null);
+    const [configIssues, setConfigIssues] = useState<ConfigIssueModel[]>([]);
+    const [metrics, setMetrics] = useState<MetricSummaryModel[]>([]);
+    const metricsModal = useModal()
 
     useEffect(() => {
         listen<string>('bot_visualizer_update', event => {
@@ -51,6 +65,18 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
             const payload = event.payload as BotConfigModel
             setConfig(payload)
         })
+
+        listen<ConfigIssueModel[]>('bot_config_validation', event => {
+            setConfigIssues(event.payload)
+        })
+
+        listen<MetricSummaryModel[]>('bot_metrics_s2c', event => {
+            setMetrics(event.payload)
+        })
+
+        listen<string>('bot_config_error', event => {
+            message(event.payload, { title: 'Config could not be loaded', type: 'error' })
+        })
     }, [])
 
     const setRunningToggle = () => {
@@ -77,6 +103,12 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
         emit('bot_config_c2s', newConfig)
     }
 
+    const setConfigValue = (patch: Partial<BotConfigModel>) => {
+        emit('bot_config_c2s', { ...config, ...patch })
+    }
+
+    const [showOverlay, setShowOverlay] = useState(false)
+
     const [lightMode, setLightMode] = useState(false)
     const minSizeFarm = [250, 200]
     const minSize = [250, 160]
@@ -102,6 +134,29 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
                             {config?.mode === 'Support' && (<SupportConfig botState={supportState} botStopWatch={suppStopWatch.watch} info={info} config={config.support_config} onChange={makeConfigUpdater('support_config')} />)}
                             {config?.mode === 'AutoShout' && (<ShoutConfig config={config.shout_config} onChange={makeConfigUpdater('shout_config')} />)}
                         </div>
+                        {showOverlay && <DebugOverlay info={info} />}
+                        <ConfigIssues issues={configIssues} />
+                        <SlotUsageAlert slotsUsage={info?.slots_usage} onRefill={() => emit('reset_slots_usage')} />
+                        <Modal isShowing={metricsModal.isShown} hide={metricsModal.close} title={<h4>Timings</h4>} body={
+                            <>
+                                <ConfigTable>
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Tick rate" helpText="Target number of bot iterations per second, idle time is spent sleeping. 0 runs as fast as the capture allows" />}
+                                        item={<NumericInput unit='/s' value={config.tick_rate} onChange={value => setConfigValue({ tick_rate: value })} />}
+                                    />
+                                    <ConfigTableRow
+                                        label={<ConfigLabel name="Recorder frames" helpText="Keep the last frames and decisions in memory, they are saved to the profile folder on death or mobs timeout. 0 disables the recorder" />}
+                                        item={<NumericInput unit='#' value={config.recorder_frames} onChange={value => setConfigValue({ recorder_frames: value })} />}
+                                    />
+                                </ConfigTable>
+                                <div className="stats">
+                                    <div className="row">
+                                        <div>Last tick: {info?.last_tick_duration}ms{info?.tick_budget ? ` / ${info.tick_budget}ms` : ''} | missed deadlines: {info?.missed_deadlines}</div>
+                                    </div>
+                                </div>
+                                <TimingMetrics metrics={metrics} />
+                            </>
+                        } />
                     </>
                 )}
 
@@ -110,6 +165,8 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
                     <div className="sm footerCombinedBtn">
                         {!isNil(config?.mode) && <div className="btn sm" onClick={toogleLightMode}>Compact</div>}
                         {!isNil(config?.mode) && <div className="btn sm" onClick={() => {invoke("focus_client")}}>Focus</div>}
+                        {!isNil(config?.mode) && <div className="btn sm" onClick={() => setShowOverlay(!showOverlay)}>Overlay</div>}
+                        {!isNil(config?.mode) && <div className="btn sm" onClick={metricsModal.open}>Timings</div>}
                     </div>
                     {!isNil(config?.mode) && <div className="btn" onClick={setRunningToggle}>{config?.is_running ? 'Disengage' : 'Engage'}</div>}
                 </div>
@@ -0,0 +1,78 @@
+import styled from 'styled-components'
+import { useEffect, useState } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import Modal from './Modal'
+import { IconTemplateModel } from '../models/BotConfig'
+
+type Props = {
+    className?: string,
+    isShowing: boolean,
+    hide: () => void,
+    onCapture: (icon: IconTemplateModel) => void,
+}
+
+const BuffIconModal = ({ className, isShowing, hide, onCapture }: Props) => {
+    const [frame, setFrame] = useState<string | null>(null)
+    const [status, setStatus] = useState('')
+
+    const capture = () => {
+        invoke('capture_calibration_frame').then((png) => {
+            setFrame(URL.createObjectURL(new Blob([new Uint8Array(png as number[])], { type: 'image/png' })))
+            setStatus('Click on the center of the buff icon, below the player stats')
+        }).catch((error) => setStatus(error as string))
+    }
+
+    useEffect(() => {
+        if (isShowing) capture()
+    }, [isShowing])
+
+    useEffect(() => () => { if (frame) URL.revokeObjectURL(frame) }, [frame])
+
+    const onFrameClick = (event: React.MouseEvent<HTMLImageElement>) => {
+        // The frame is displayed scaled down, map the click back to capture pixels
+        const image = event.currentTarget
+        const rect = image.getBoundingClientRect()
+        const x = Math.floor((event.clientX - rect.left) * image.naturalWidth / rect.width)
+        const y = Math.floor((event.clientY - rect.top) * image.naturalHeight / rect.height)
+
+        invoke('capture_buff_icon', { x, y }).then((icon) => {
+            onCapture(icon as IconTemplateModel)
+            hide()
+        }).catch((error) => setStatus(error as string))
+    }
+
+    return (
+        <Modal className={className} isShowing={isShowing} hide={hide} title={<h4>Buff icon</h4>} body={
+            <div className="capture">
+                <div className="row">
+                    <button className="btn sm" onClick={capture}>Capture</button>
+                </div>
+                {frame && <img className="frame" alt="Client capture" src={frame} onClick={onFrameClick} />}
+                <div className="status">{status}</div>
+            </div>
+        }/>
+    )
+}
+
+export default styled(BuffIconModal)`
+    & .capture {
+        display: flex;
+        flex-direction: column;
+        gap: .5rem;
+    }
+
+    & .row {
+        display: flex;
+        justify-content: flex-end;
+    }
+
+    & .frame {
+        width: 100%;
+        cursor: crosshair;
+    }
+
+    & .status {
+        text-align: center;
+    }
+`
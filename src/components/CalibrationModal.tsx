@@ -0,0 +1,94 @@
+import styled from 'styled-components'
+import { useEffect, useState } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import Modal from './Modal'
+import { CalibrationTarget, calibrationTargets, FarmingConfigModel } from '../models/BotConfig'
+
+type Props = {
+    className?: string,
+    isShowing: boolean,
+    hide: () => void,
+    config: FarmingConfigModel,
+    onChange: (config: FarmingConfigModel) => void,
+}
+
+const translateTarget = (target: CalibrationTarget) => {
+    switch (target) {
+        case 'PassiveMob': return 'Passive mob name'
+        case 'AggressiveMob': return 'Aggressive mob name'
+        case 'HpBar': return 'HP bar'
+        case 'MpBar': return 'MP bar'
+        case 'FpBar': return 'FP bar'
+    }
+}
+
+const CalibrationModal = ({ className, isShowing, hide, config, onChange }: Props) => {
+    const [target, setTarget] = useState<CalibrationTarget>('PassiveMob')
+    const [frame, setFrame] = useState<string | null>(null)
+    const [status, setStatus] = useState('')
+
+    const capture = () => {
+        invoke('capture_calibration_frame').then((png) => {
+            setFrame(URL.createObjectURL(new Blob([new Uint8Array(png as number[])], { type: 'image/png' })))
+            setStatus(`Click on the ${translateTarget(target).toLowerCase()}`)
+        }).catch((error) => setStatus(error as string))
+    }
+
+    useEffect(() => {
+        if (isShowing) capture()
+    }, [isShowing])
+
+    useEffect(() => () => { if (frame) URL.revokeObjectURL(frame) }, [frame])
+
+    const onFrameClick = (event: React.MouseEvent<HTMLImageElement>) => {
+        // The frame is displayed scaled down, map the click back to capture pixels
+        const image = event.currentTarget
+        const rect = image.getBoundingClientRect()
+        const x = Math.floor((event.clientX - rect.left) * image.naturalWidth / rect.width)
+        const y = Math.floor((event.clientY - rect.top) * image.naturalHeight / rect.height)
+
+        invoke('calibrate', { target, x, y, config }).then((newConfig) => {
+            onChange(newConfig as FarmingConfigModel)
+            setStatus(`${translateTarget(target)} calibrated`)
+        }).catch((error) => setStatus(error as string))
+    }
+
+    return (
+        <Modal className={className} isShowing={isShowing} hide={hide} title={<h4>Calibration</h4>} body={
+            <div className="calibration">
+                <div className="row">
+                    <select value={target} onChange={event => setTarget(event.target.value as CalibrationTarget)}>
+                        {calibrationTargets.map(target => <option key={target} value={target}>{translateTarget(target)}</option>)}
+                    </select>
+                    <button className="btn sm" onClick={capture}>Capture</button>
+                </div>
+                {frame && <img className="frame" alt="Client capture" src={frame} onClick={onFrameClick} />}
+                <div className="status">{status}</div>
+            </div>
+        }/>
+    )
+}
+
+export default styled(CalibrationModal)`
+    & .calibration {
+        display: flex;
+        flex-direction: column;
+        gap: .5rem;
+    }
+
+    & .row {
+        display: flex;
+        justify-content: space-between;
+        gap: .5rem;
+    }
+
+    & .frame {
+        width: 100%;
+        cursor: crosshair;
+    }
+
+    & .status {
+        text-align: center;
+    }
+`
@@ -0,0 +1,49 @@
+import styled from 'styled-components'
+
+import { ConfigIssueModel } from '../models/BotConfig'
+
+type Props = {
+    className?: string,
+    issues: ConfigIssueModel[],
+}
+
+const ConfigIssues = ({ className, issues }: Props) => {
+    if (issues.length === 0) return null
+
+    const isRefused = issues.some(issue => issue.severity === 'Error')
+    return (
+        <div className={className}>
+            {isRefused && <div className="title">Last change was not applied</div>}
+            {issues.map((issue, index) => (
+                <div key={index} className="issue" data-severity={issue.severity}>
+                    {issue.severity === 'Error' ? '⛔' : '⚠️'} <span className="path">{issue.path}</span>: {issue.message}
+                </div>
+            ))}
+        </div>
+    )
+}
+
+export default styled(ConfigIssues)`
+    background: hsla(203, 100%, 0%, .75);
+    backdrop-filter: blur(.5rem);
+    border-radius: .25rem;
+    margin: .25rem .5rem;
+    padding: .25rem .5rem;
+    max-height: 6rem;
+    overflow-y: auto;
+    color: white;
+    font-size: .8rem;
+
+    & .title {
+        font-weight: bold;
+        color: hsl(0, 100%, 70%);
+    }
+
+    & .issue[data-severity="Error"] {
+        color: hsl(0, 100%, 70%);
+    }
+
+    & .path {
+        font-family: monospace;
+    }
+`
@@ -0,0 +1,68 @@
+import styled from 'styled-components'
+
+import { Bounds, FrontendInfoModel, Target } from '../models/FrontendInfo'
+
+type Props = {
+    className?: string,
+    info: FrontendInfoModel | null,
+}
+
+const targetColor = (target: Target) => {
+    if (target.target_type === "TargetMarker") return "hsl(350, 90%, 66%)"
+    return target.target_type.Mob === "Aggressive" ? "hsl(0, 77%, 40%)" : "hsl(60, 67%, 75%)"
+}
+
+const Rect = ({ bounds, color, dashed = false, width = 1 }: { bounds: Bounds, color: string, dashed?: boolean, width?: number }) => (
+    <rect x={bounds.x} y={bounds.y} width={bounds.w} height={bounds.h} fill="none" stroke={color} strokeWidth={width} strokeDasharray={dashed ? "4 2" : undefined} />
+)
+
+// Draws what the bot detected during its last iteration, in capture coordinates
+const DebugOverlay = ({ className, info }: Props) => {
+    const [width, height] = info?.frame_size ?? [800, 600]
+    const active = info?.active_enemy_bounds
+
+    return (
+        <div className={className}>
+            <svg viewBox={`0 0 ${width} ${height}`}>
+                {info?.stat_bars_bounds?.map(([kind, bounds]) => (
+                    <g key={kind}>
+                        <Rect bounds={bounds} color="hsl(120, 60%, 50%)" />
+                        <text x={bounds.x + bounds.w + 4} y={bounds.y + bounds.h}>{kind}</text>
+                    </g>
+                ))}
+                {info?.avoided_bounds?.map((bounds, index) => (
+                    <Rect key={index} bounds={bounds} color="hsl(0, 0%, 60%)" dashed />
+                ))}
+                {info?.enemy_bounds?.map((target, index) => (
+                    <Rect key={index} bounds={target.bounds} color={targetColor(target)} />
+                ))}
+                {info?.target_marker_bounds && (
+                    <Rect bounds={info.target_marker_bounds} color="hsl(350, 90%, 66%)" width={2} />
+                )}
+                {active && (
+                    <g>
+                        <Rect bounds={active.bounds} color="white" width={3} />
+                        {/* Where the bot clicks, see Target::get_attack_coords */}
+                        <circle cx={active.bounds.x + Math.floor(active.bounds.w / 2)} cy={active.bounds.y + active.bounds.h + 10} r={3} fill="white" />
+                    </g>
+                )}
+            </svg>
+        </div>
+    )
+}
+
+export default styled(DebugOverlay)`
+    width: 100%;
+    padding: .5rem;
+    background: hsla(0, 0%, 0%, .75);
+
+    & svg {
+        width: 100%;
+        border: 1px solid hsl(48, 50%, 43%);
+    }
+
+    & text {
+        fill: white;
+        font-size: 10px;
+    }
+`
@@ -0,0 +1,70 @@
+import styled from 'styled-components'
+
+import { SessionRecordModel } from '../models/FrontendInfo'
+
+type Props = {
+    className?: string,
+    sessions: SessionRecordModel[],
+}
+
+const formatDuration = (seconds: number) => {
+    const hours = Math.floor(seconds / 3600)
+    const minutes = Math.floor((seconds % 3600) / 60)
+    return hours > 0 ? `${hours}h${minutes.toString().padStart(2, '0')}` : `${minutes}m`
+}
+
+const formatEntries = (entries: Record<string, number>, format: (value: number) => string) =>
+    Object.entries(entries).map(([name, value]) => `${name}: ${format(value)}`).join(', ')
+
+const SessionHistory = ({ className, sessions }: Props) => {
+    if (sessions.length === 0) return <div className={className}>No sessions yet, engage the bot first</div>
+
+    return (
+        <table className={className}>
+            <thead>
+                <tr>
+                    <th>Started</th>
+                    <th>Mode</th>
+                    <th>Duration</th>
+                    <th>Kills</th>
+                    <th>Kills/h</th>
+                    <th>Deaths</th>
+                    <th>Slots used</th>
+                    <th>Time per state</th>
+                </tr>
+            </thead>
+            <tbody>
+                {[...sessions].reverse().map(session => {
+                    const duration = session.ended_at - session.started_at
+                    return (
+                        <tr key={session.started_at}>
+                            <td>{new Date(session.started_at * 1000).toLocaleString()}</td>
+                            <td>{session.mode}</td>
+                            <td>{formatDuration(duration)}</td>
+                            <td>{session.kills}</td>
+                            <td>{duration > 0 ? (session.kills * 3600 / duration).toFixed(0) : 0}</td>
+                            <td>{session.deaths}</td>
+                            <td className="details">{formatEntries(session.slot_uses, String)}</td>
+                            <td className="details">{formatEntries(session.state_durations, (ms) => formatDuration(ms / 1000))}</td>
+                        </tr>
+                    )
+                })}
+            </tbody>
+        </table>
+    )
+}
+
+export default styled(SessionHistory)`
+    width: 100%;
+    font-size: .8rem;
+    border-collapse: collapse;
+
+    & th, & td {
+        padding: .1rem .4rem;
+        text-align: right;
+    }
+
+    & th:first-child, & td:first-child, & .details {
+        text-align: left;
+    }
+`
@@ -2,8 +2,9 @@ import styled from 'styled-components'
 
 import Slot from './Slot'
 
-import { createSlotBars, FarmingConfigModel, SlotModel, SupportConfigModel } from '../models/BotConfig'
+import { createSlotBars, FarmingConfigModel, IconTemplateModel, SlotModel, SupportConfigModel } from '../models/BotConfig'
 import SlotModal from './SlotModal'
+import BuffIconModal from './BuffIconModal'
 import useModal from './utils/UseModal'
 import { useState } from 'react'
 import { useKeyPress } from './utils/KeyboardHotkeys'
@@ -16,7 +17,9 @@ type Props = {
 }
 
 const SlotBar = ({ className, config, botMode, onChange }: Props) => {
-    const { isShown, toggle } = useModal();
+    const slotModal = useModal();
+    const { isShown, toggle } = slotModal;
+    const buffIconModal = useModal(slotModal);
     const [currentSlotId, setCurrentSlotId] = useState(-1)
     const [currentBarIndex, setCurrentBarIndex] = useState(0)
     const toogleSlot = (id: number, event: any,targetSlot: SlotModel ) => {
@@ -41,9 +44,18 @@ const SlotBar = ({ className, config, botMode, onChange }: Props) => {
         onChange(newConfig)
     }
 
+    const isCurrentSlot = (slot_bar_index: number, slot_index: number) => slot_bar_index === currentBarIndex && slot_index === currentSlotId
+    const hasBuffIcon = (config.buff_icons ?? []).some(buffIcon => isCurrentSlot(buffIcon.slot_bar_index, buffIcon.slot_index))
+    const handleBuffIconChange = (icon?: IconTemplateModel) => {
+        const otherIcons = (config.buff_icons ?? []).filter(buffIcon => !isCurrentSlot(buffIcon.slot_bar_index, buffIcon.slot_index))
+        onChange({ ...config, buff_icons: icon ? [...otherIcons, { slot_bar_index: currentBarIndex, slot_index: currentSlotId, icon }] : otherIcons })
+    }
+
     return (
         <>
-            <SlotModal botMode={botMode} isShowing={isShown} hide={toggle} index={currentSlotId} slot={slots[currentBarIndex].slots[currentSlotId]} onChange={handleSlotChange} barIndex={currentBarIndex} indexName={currentSlotId +""}/>
+            <SlotModal botMode={botMode} isShowing={isShown} hide={toggle} index={currentSlotId} slot={slots[currentBarIndex].slots[currentSlotId]} onChange={handleSlotChange} barIndex={currentBarIndex} indexName={currentSlotId +""}
+                hasBuffIcon={hasBuffIcon} onCaptureBuffIcon={buffIconModal.open} onClearBuffIcon={() => handleBuffIconChange()}/>
+            <BuffIconModal isShowing={buffIconModal.isShown} hide={buffIconModal.close} onCapture={icon => handleBuffIconChange(icon)} />
             <div className={className}>
 
                 <div className="slots">
@@ -1,5 +1,5 @@
 import styled from 'styled-components'
-import { cooldownSlotTypes, farmingSlotsBlacklist, SlotModel, SlotType, slotTypes, supportSlotsBlacklist, thresholdSlotTypes, translateDesc } from '../models/BotConfig'
+import { cooldownSlotTypes, farmingSlotsBlacklist, maxUsesSlotTypes, SlotModel, SlotType, slotTypes, supportSlotsBlacklist, thresholdSlotTypes, translateDesc } from '../models/BotConfig'
 import ConfigLabel from './config/ConfigLabel'
 import ConfigTableRow from './config/ConfigTableRow'
 import NumericInput from './config/NumericInput'
@@ -19,10 +19,12 @@ type Props = {
     barIndex: number,
     indexName: string,
     botMode: string,
-
+    hasBuffIcon: boolean,
+    onCaptureBuffIcon: () => void,
+    onClearBuffIcon: () => void,
 }
 
-const SlotModal = ({className, isShowing, hide, index, slot, onChange, barIndex, indexName, botMode}: Props) => {
+const SlotModal = ({className, isShowing, hide, index, slot, onChange, barIndex, indexName, botMode, hasBuffIcon, onCaptureBuffIcon, onClearBuffIcon}: Props) => {
     const blackList = botMode == "farming"? farmingSlotsBlacklist : supportSlotsBlacklist
     const options = slotTypes.map((type)=>{
         if (!blackList.includes(type))
@@ -47,7 +49,7 @@ const SlotModal = ({className, isShowing, hide, index, slot, onChange, barIndex,
                     {cooldownSlotTypes.includes(slot.slot_type) &&
                         <ConfigTableRow
                             layout="v"
-                            label={<ConfigLabel name="Cooldown" helpText="Interval between two usage. Format must be : hh:mm:ss:mss" />}
+                            label={<ConfigLabel name="Cooldown" helpText="Interval between two usage, skills use it when cooldown detection is disabled or the slot bar is hidden. Format must be : hh:mm:ss:mss" />}
                             item={<TimeInput value={slot.slot_cooldown} onChange={value => {slot.slot_cooldown = value;onChange(barIndex, index, slot)}} />}
                         />
                     }
@@ -60,6 +62,33 @@ const SlotModal = ({className, isShowing, hide, index, slot, onChange, barIndex,
                         />
                     }
 
+                    {slot.slot_type == "BuffSkill" &&
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Buff duration" helpText="How long the buff lasts, it is recast right before it runs out. Buffs without a duration are cast in turns every interval between buffs. Format must be : hh:mm:ss:mss" />}
+                            item={<TimeInput value={slot.slot_buff_duration ?? 0} onChange={value => {slot.slot_buff_duration = value;onChange(barIndex, index, slot)}} />}
+                        />
+                    }
+
+                    {botMode == "farming" && slot.slot_type == "BuffSkill" && (slot.slot_buff_duration ?? 0) > 0 &&
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Buff icon" helpText="Icon of the buff below the player stats, the buff is recast as soon as it is missing when buff detection is enabled. The duration is used when the icon can't be seen." />}
+                            item={<div className="buffIcon">
+                                <button className="btn sm" onClick={onCaptureBuffIcon}>{hasBuffIcon ? "Capture again" : "Capture"}</button>
+                                {hasBuffIcon && <button className="btn sm" onClick={onClearBuffIcon}>Clear</button>}
+                            </div>}
+                        />
+                    }
+
+                    {maxUsesSlotTypes.includes(slot.slot_type) &&
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Max uses" helpText="Size of the stack, the slot is not used anymore once it is assumed empty. 0 means no limit." />}
+                            item={<NumericInput min={0} value={slot.slot_max_uses ?? 0} onChange={value => {slot.slot_max_uses = value;onChange(barIndex, index, slot)}} />}
+                        />
+                    }
+
                     <ConfigTableRow
                         layout="v"
                         label={<ConfigLabel name="Enabled" />}
@@ -76,6 +105,10 @@ const SlotModal = ({className, isShowing, hide, index, slot, onChange, barIndex,
 
 
 export default styled(SlotModal)`
+& .buffIcon {
+    display: flex;
+    gap: .5rem;
+}
 & img.type {
     width: 100%;
     height: 100%;
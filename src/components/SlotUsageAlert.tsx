@@ -0,0 +1,41 @@
+import styled from 'styled-components'
+
+import { SlotUsageModel } from '../models/FrontendInfo'
+
+type Props = {
+    className?: string,
+    slotsUsage?: SlotUsageModel[],
+    onRefill: () => void,
+}
+
+const SlotUsageAlert = ({ className, slotsUsage, onRefill }: Props) => {
+    const exhausted = (slotsUsage ?? []).filter(usage => usage.is_exhausted)
+    if (exhausted.length === 0) return null
+
+    return (
+        <div className={className}>
+            <div className="title">Slots assumed empty, they are not used anymore</div>
+            {exhausted.map(usage => (
+                <div key={`${usage.slot_bar_index}-${usage.slot_index}`} className="slot">
+                    ⚠️ F{usage.slot_bar_index + 1}-{usage.slot_index} {usage.slot_type}: {usage.uses}/{usage.max_uses} uses
+                </div>
+            ))}
+            <div className="btn sm" onClick={onRefill}>Refilled</div>
+        </div>
+    )
+}
+
+export default styled(SlotUsageAlert)`
+    background: hsla(203, 100%, 0%, .75);
+    backdrop-filter: blur(.5rem);
+    border-radius: .25rem;
+    margin: .25rem .5rem;
+    padding: .25rem .5rem;
+    color: white;
+    font-size: .8rem;
+
+    & .title {
+        font-weight: bold;
+        color: hsl(40, 100%, 60%);
+    }
+`
@@ -0,0 +1,55 @@
+import styled from 'styled-components'
+
+import { MetricSummaryModel } from '../models/FrontendInfo'
+
+type Props = {
+    className?: string,
+    metrics: MetricSummaryModel[],
+}
+
+const formatMs = (value: number) => `${value.toFixed(1)}ms`
+
+const TimingMetrics = ({ className, metrics }: Props) => {
+    if (metrics.length === 0) return <div className={className}>No timings yet, engage the bot first</div>
+
+    return (
+        <table className={className}>
+            <thead>
+                <tr>
+                    <th>Step</th>
+                    <th>Count</th>
+                    <th>p50</th>
+                    <th>p95</th>
+                    <th>Max</th>
+                </tr>
+            </thead>
+            <tbody>
+                {metrics.map(metric => (
+                    <tr key={metric.label}>
+                        <td className="label">{metric.label}</td>
+                        <td>{metric.count}</td>
+                        <td>{formatMs(metric.p50)}</td>
+                        <td>{formatMs(metric.p95)}</td>
+                        <td>{formatMs(metric.max)}</td>
+                    </tr>
+                ))}
+            </tbody>
+        </table>
+    )
+}
+
+export default styled(TimingMetrics)`
+    width: 100%;
+    font-size: .8rem;
+    border-collapse: collapse;
+
+    & th, & td {
+        padding: .1rem .4rem;
+        text-align: right;
+    }
+
+    & .label, & th:first-child {
+        font-family: monospace;
+        text-align: left;
+    }
+`
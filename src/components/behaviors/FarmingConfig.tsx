@@ -8,7 +8,7 @@ import NumericInput from '../config/NumericInput'
 import ColorSelector from '../config/ColorSelector'
 
 import SlotBar from '../SlotBar'
-import { createSlotBars, FarmingConfigModel } from '../../models/BotConfig'
+import { createSlotBars, FarmingConfigModel, formatRotationSlots, parseRotationSlots, RotationSlotModel, SkillRotationModel } from '../../models/BotConfig'
 import { useEffect, useRef, useState } from 'react'
 import { FrontendInfoModel } from '../../models/FrontendInfo'
 import Modal from '../Modal'
@@ -17,6 +17,8 @@ import YesNoModal from '../YesNoModal'
 import { MsFormat, StopWatchValues } from '../utils/StopWatch'
 import { DefaultValuesChecker } from '../utils/DefaultValuesChecker'
 import TimeInput from '../config/TimeInput'
+import CalibrationModal from '../CalibrationModal'
+import StringList from '../config/StringList'
 
 type Props = {
     className?: string,
@@ -36,6 +38,8 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
     const mobsColorsDebugModal = useModal(mobsNameDebugModal)
     const resetSlotYesNo = useModal(debugModal)
     const obstacleAvoidanceDebugModal = useModal(debugModal)
+    const calibrationModal = useModal(mobsNameDebugModal)
+    const skillRotationModal = useModal(debugModal)
 
     const selectedMobType = useRef(0)
 
@@ -48,6 +52,9 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
         'obstacle_avoidance_max_try': 3,
         'min_mobs_name_width': 15,
         'max_mobs_name_width': 180,
+        'mobs_name_dilation_x': 2,
+        'mobs_name_dilation_y': 1,
+        'mobs_scan_downscale': 1,
         'circle_pattern_rotation_duration': 30,
         'min_hp_attack': 30,
         'prevent_already_attacked': true,
@@ -61,6 +68,14 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
         () => onChange({...config, ...{aggressive_mobs_colors: defaultValues['aggressive_mobs_colors'], aggressive_tolerence: defaultValues['aggressive_tolerence']} })
     ]
 
+    const skillRotation = config.skill_rotation ?? {}
+    const setSkillRotation = (rotation: SkillRotationModel) => onChange({...config, skill_rotation: {...skillRotation, ...rotation}})
+    const rotationPhases: [string, keyof SkillRotationModel, string][] = [
+        ['Opener', 'opener', 'Skills used once at the start of every fight, in order'],
+        ['Filler', 'filler', 'Skills used in a loop after the opener'],
+        ['Finisher', 'finisher', 'Skills used in a loop once the target HP is low enough'],
+    ]
+
     const [debugMode, setDebugMode] = useState(false);
     useEffect(() => {
         if (debugMode) {
@@ -96,9 +111,25 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                         item={<button onClick={onDeathModal.open}>⚙️</button>}
                     />
                     <ConfigTableRow
-                        label={<ConfigLabel name="Interval between buffs" helpText="" />}
+                        label={<ConfigLabel name="Interval between buffs" helpText="Time between two buffs without a buff duration, buffs with a duration are recast when they run out" />}
                         item={<TimeInput value={config.interval_between_buffs} onChange={value => onChange({...config, interval_between_buffs: value})} />}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Skill rotation" helpText="Attack skills used in a fixed order instead of the lowest threshold attack skill" />}
+                        item={<button onClick={skillRotationModal.open}>⚙️</button>}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Empty slots detection" helpText="Skip food, pills and restorers whose icon looks empty or greyed out on the visible slot bar and use the next matching slot instead" />}
+                        item={<BooleanSlider value={config.empty_slots_detection ?? false} onChange={value => onChange({...config, empty_slots_detection: value})} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Cooldown detection" helpText="Read attack, buff and heal skill cooldowns from the visible slot bar, the configured cooldowns are used when it can't tell" />}
+                        item={<BooleanSlider value={config.cooldown_detection ?? false} onChange={value => onChange({...config, cooldown_detection: value})} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Buff detection" helpText="Look for the captured icons of buffs with a duration below the player stats and recast the missing ones, the durations are used when the icons can't be seen" />}
+                        item={<BooleanSlider value={config.buff_detection ?? false} onChange={value => onChange({...config, buff_detection: value})} />}
+                    />
                     <ConfigTableRow
                         label={<ConfigLabel name="Debug" />}
                         item={<BooleanSlider value={debugMode} onChange={value => setDebugMode(value)} />}
@@ -128,6 +159,24 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                     />
                 </ConfigTable>
             }/>
+            <Modal isShowing={skillRotationModal.isShown} hide={skillRotationModal.close} title={<h4>Skill rotation</h4>} body={
+                <ConfigTable>
+                    {rotationPhases.map(([name, phase, helpText]) => (
+                        <ConfigTableRow
+                            key={phase}
+                            layout="v"
+                            label={<ConfigLabel name={name} helpText={`${helpText}. One slot per line written as its slot bar and slot keys, e.g. F1-3`} />}
+                            item={<StringList messages={formatRotationSlots(skillRotation[phase] as RotationSlotModel[] | undefined)} onChange={value => setSkillRotation({[phase]: parseRotationSlots(value)})} />}
+                        />
+                    ))}
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Finisher target HP" helpText="The finisher is used once the target HP is at or below this value. Default : 30" />}
+                        item={<NumericInput min={0} max={100} unit='%' value={skillRotation.finisher_target_hp ?? 30} onChange={value => setSkillRotation({finisher_target_hp: value})} />}
+                    />
+                </ConfigTable>
+            }/>
+            <CalibrationModal isShowing={calibrationModal.isShown} hide={calibrationModal.close} config={config} onChange={onChange} />
             <Modal isShowing={obstacleAvoidanceDebugModal.isShown} hide={obstacleAvoidanceDebugModal.close} title={<h4>Avoidances</h4>} body={
                 <ConfigTable>
                     <ConfigTableRow
@@ -177,7 +226,27 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                             label={<ConfigLabel name="Max mobs name width" helpText="" />}
                             item={<NumericInput unit='px' value={config.max_mobs_name_width} onChange={value => onChange({...config, max_mobs_name_width: value})} />}
                         />
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Name horizontal dilation" helpText="Gap in pixels bridged between letters of a mob name. Increase it if names with spaces are detected as several mobs. Default : 2" />}
+                            item={<NumericInput unit='px' value={config.mobs_name_dilation_x} onChange={value => onChange({...config, mobs_name_dilation_x: value})} />}
+                        />
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Name vertical dilation" helpText="Gap in pixels bridged between rows of a mob name. Default : 1" />}
+                            item={<NumericInput unit='px' value={config.mobs_name_dilation_y} onChange={value => onChange({...config, mobs_name_dilation_y: value})} />}
+                        />
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Name scan downscale" helpText="Look for mob names on a grid this many times smaller first, then scan around what was found. Higher values are faster but may miss small names. 1 scans every pixel. Default : 1" />}
+                            item={<NumericInput min={1} unit='x' value={config.mobs_scan_downscale} onChange={value => onChange({...config, mobs_scan_downscale: value})} />}
+                        />
                     </> }
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Calibration" helpText="Derive mob name and status bar colors by clicking on them in a capture of the client" />}
+                        item={<button onClick={calibrationModal.open}>⚙️</button>}
+                    />
                     <ConfigTableRow
                         layout="v"
                         label={<ConfigLabel name="Circle pattern duration" helpText="The bot will try to move in a circle pattern to find targets. Value of 0 will stay in place. Lower the value to increase circle size. Default : 30" />}
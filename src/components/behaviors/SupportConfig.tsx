@@ -60,9 +60,17 @@ const SupportConfig = ({ className, info, config, onChange, botStopWatch, botSta
                         item={<TimeInput value={config.obstacle_avoidance_cooldown} onChange={value => onChange?.({...config, obstacle_avoidance_cooldown: value})} />}
                     />
                     <ConfigTableRow
-                        label={<ConfigLabel name="Interval between buffs" helpText="" />}
+                        label={<ConfigLabel name="Interval between buffs" helpText="Time between two buffs without a buff duration, buffs with a duration are recast when they run out" />}
                         item={<TimeInput value={config.interval_between_buffs} onChange={value => onChange({...config, interval_between_buffs: value})} />}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Empty slots detection" helpText="Skip food, pills and restorers whose icon looks empty or greyed out on the visible slot bar and use the next matching slot instead" />}
+                        item={<BooleanSlider value={config.empty_slots_detection ?? false} onChange={value => onChange({...config, empty_slots_detection: value})} />}
+                    />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Cooldown detection" helpText="Read attack, buff and heal skill cooldowns from the visible slot bar, the configured cooldowns are used when it can't tell" />}
+                        item={<BooleanSlider value={config.cooldown_detection ?? false} onChange={value => onChange({...config, cooldown_detection: value})} />}
+                    />
                     <ConfigTableRow
                         label={<ConfigLabel name="Reset all slots" helpText="" />}
                         item={<button onClick={() => resetSlotYesNo.open()}>⚙️</button>}
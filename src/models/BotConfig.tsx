@@ -8,12 +8,16 @@ export type FixedArray<TItem, TLength extends number> = [TItem, ...TItem[]] & {
 
 export const slotTypes = ["Unused", "Food", "Pill", "HealSkill", "MpRestorer", "FpRestorer", "PickupPet", "PickupMotion", "AttackSkill", "BuffSkill", "RezSkill", "Flying"] as const;
 export const thresholdSlotTypes = ["Food", "Pill", "HealSkill", "MpRestorer", "FpRestorer"];
+export const maxUsesSlotTypes = ["Food", "Pill", "MpRestorer", "FpRestorer"];
 export const cooldownSlotTypes = ["Food", "Pill", "HealSkill", "AttackSkill", "BuffSkill", "MpRestorer", "FpRestorer", "PickupPet"];
 export const farmingSlotsBlacklist = ["HealSkill", "Flying", "RezSkill"]
 export const supportSlotsBlacklist = ["PickupPet", "PickupMotion", "AttackSkill"]
 
 export type SlotType = typeof slotTypes[number];
 
+export const calibrationTargets = ["PassiveMob", "AggressiveMob", "HpBar", "MpBar", "FpBar"] as const;
+export type CalibrationTarget = typeof calibrationTargets[number];
+
 export const createSlotBars = () => (
     [...new Array(9)].map(_ => ({slots:[...new Array(10)].map(_ => ({ slot_type: 'Unused', slot_enabled: false } as SlotModel))})) as SlotBars
 )
@@ -58,6 +62,8 @@ export type SlotModel = {
     slot_cooldown?: number,
     slot_threshold?: number,
     slot_enabled: boolean,
+    slot_max_uses?: number,
+    slot_buff_duration?: number,
 }
 export type SlotBarHolder = {
     slots: SlotBarModel
@@ -68,6 +74,42 @@ export type SlotBars = FixedArray<SlotBarHolder, 9>
 
 export type ModeModel = "Farming" | "Support" | "AutoShout"
 
+export type IconTemplateModel = {
+    width: number,
+    height: number,
+    pixels: number[][],
+}
+
+export type BuffIconModel = {
+    slot_bar_index: number,
+    slot_index: number,
+    icon: IconTemplateModel,
+}
+
+export type RotationSlotModel = {
+    slot_bar_index: number,
+    slot_index: number,
+}
+
+export type SkillRotationModel = {
+    opener?: RotationSlotModel[],
+    filler?: RotationSlotModel[],
+    finisher?: RotationSlotModel[],
+    finisher_target_hp?: number,
+}
+
+// Rotation steps are written like the slot keys, e.g. "F1-3" for the slot 3 of the first slot bar
+export const formatRotationSlots = (slots?: RotationSlotModel[]) => (
+    (slots ?? []).map(slot => `F${slot.slot_bar_index + 1}-${slot.slot_index}`)
+)
+
+export const parseRotationSlots = (lines: string[]) => (
+    lines.flatMap(line => {
+        const match = line.trim().toUpperCase().match(/^F([1-9])-([0-9])$/)
+        return match ? [{ slot_bar_index: Number(match[1]) - 1, slot_index: Number(match[2]) }] : []
+    })
+)
+
 export type FarmingConfigModel = Partial<{
     [key: string]: any;
     on_demand_pet: boolean,
@@ -80,6 +122,7 @@ export type FarmingConfigModel = Partial<{
     passive_tolerence: number;
     aggressive_mobs_colors: number[];
     aggressive_tolerence: number;
+    status_bar_colors: StatusBarColorsModel;
 
     is_stop_fighting: boolean;
     prevent_already_attacked: boolean;
@@ -89,19 +132,38 @@ export type FarmingConfigModel = Partial<{
 
     min_mobs_name_width: number,
     max_mobs_name_width: number,
+    mobs_name_dilation_x: number,
+    mobs_name_dilation_y: number,
+    mobs_scan_downscale: number,
 
     min_hp_attack: number,
     on_death_disconnect: boolean,
     interval_between_buffs: number,
     mobs_timeout: number,
+    empty_slots_detection: boolean,
+    cooldown_detection: boolean,
+    skill_rotation: SkillRotationModel,
+    buff_detection: boolean,
+    buff_icons: BuffIconModel[],
 }>
 
+export type StatusBarColorsModel = {
+    hp?: number[][],
+    mp?: number[][],
+    fp?: number[][],
+    hp_tolerance?: number,
+    mp_tolerance?: number,
+    fp_tolerance?: number,
+}
+
 export type SupportConfigModel = Partial<{
     [key: string]: any;
     slot_bars: SlotBars,
     obstacle_avoidance_cooldown: number,
     on_death_disconnect: boolean,
     interval_between_buffs: number,
+    empty_slots_detection: boolean,
+    cooldown_detection: boolean,
 }>
 
 export type ShoutConfigModel = Partial<{
@@ -110,13 +172,42 @@ export type ShoutConfigModel = Partial<{
     shout_messages: string[],
 }>
 
+export type HudRegionModel = {
+    min_x: number,
+    min_y: number,
+    max_x: number,
+    max_y: number,
+}
+
+export type HudLayoutModel = {
+    width: number,
+    height: number,
+    stat_bars: HudRegionModel,
+    target_hp_bar: HudRegionModel,
+    target_mp_bar: HudRegionModel,
+    stat_tray: HudRegionModel,
+    action_bar: HudRegionModel,
+    buff_area: HudRegionModel,
+    ignore_area_bottom: number,
+}
+
 export type BotConfigModel = {
+    version: number,
     change_id: number,
     is_running: boolean,
     mode?: ModeModel,
     farming_config: FarmingConfigModel,
     support_config: SupportConfigModel,
     shout_config: ShoutConfigModel,
+    hud_layout?: HudLayoutModel,
+    recorder_frames?: number,
+    tick_rate?: number,
 }
 
 export type AnyConfig = FarmingConfigModel | SupportConfigModel | ShoutConfigModel
+
+export type ConfigIssueModel = {
+    severity: 'Warning' | 'Error',
+    path: string,
+    message: string,
+}
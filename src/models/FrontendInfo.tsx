@@ -1,6 +1,18 @@
+export type Bounds = { x: number, y: number, w: number, h: number }
+
+export type TargetType = { Mob: "Passive" | "Aggressive" } | "TargetMarker"
+
+export type Target = { target_type: TargetType, bounds: Bounds }
+
+export type StatusBarKind = "Hp" | "Mp" | "Fp" | "TargetHP" | "TargetMP"
+
 export type FrontendInfoModel = {
-    /* enemy_bounds?: Bounds[],
-    active_enemy_bounds?: Bounds, */
+    frame_size?: [number, number],
+    enemy_bounds?: Target[],
+    active_enemy_bounds?: Target,
+    target_marker_bounds?: Bounds,
+    avoided_bounds?: Bounds[],
+    stat_bars_bounds?: [StatusBarKind, Bounds][],
     enemy_kill_count: number,
     is_attacking: boolean,
     is_running: boolean,
@@ -9,5 +21,37 @@ export type FrontendInfoModel = {
     last_search_duration: number,
     kill_min_avg: number,
     kill_hour_avg: number,
+    last_tick_duration: number,
+    tick_budget?: number,
+    missed_deadlines: number,
+    slots_usage?: SlotUsageModel[],
     version: number[],
 }
+
+export type SlotUsageModel = {
+    slot_bar_index: number,
+    slot_index: number,
+    slot_type: string,
+    uses: number,
+    max_uses?: number,
+    is_exhausted: boolean,
+}
+
+export type MetricSummaryModel = {
+    label: string,
+    count: number,
+    mean: number,
+    p50: number,
+    p95: number,
+    max: number,
+}
+
+export type SessionRecordModel = {
+    started_at: number,
+    ended_at: number,
+    mode: "Farming" | "Support" | "AutoShout",
+    kills: number,
+    deaths: number,
+    slot_uses: Record<string, number>,
+    state_durations: Record<string, number>,
+}